@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet};
+
+use super::Analyzer;
+use crate::parser::syntax;
+use crate::{DiagnosticCode, DialogueLine, DialogueStep, SourceLocation};
+
+/// How a section leads on to other sections, considering only steps which can play.
+#[derive(Debug, Default)]
+struct SectionFlow {
+    /// Sections navigated to by jumps
+    jump_targets: Vec<usize>,
+    /// Sections navigated to by bounces, which return to the section unless they terminate
+    bounce_targets: Vec<usize>,
+    /// Sections bounced to every time the section plays, along with where each bounce is
+    unconditional_bounces: Vec<(usize, Option<SourceLocation>)>,
+    /// Whether the section can end directly, with `=> END` or `=> TERMINATE`
+    ends: bool,
+    /// Whether the section can end the whole dialogue directly, with `=> TERMINATE`, even when
+    /// bounced to
+    terminates: bool,
+    /// Whether the section can run out of steps, running on into the next section
+    runs_on: bool,
+}
+
+/// State of the flow checks of a section in progress.
+struct FlowChecker<'a, 'b> {
+    analyzer: &'b mut Analyzer<'a>,
+    section_indices: &'b HashMap<&'a str, usize>,
+    flow: SectionFlow,
+    /// Whether the current step plays every time the section does
+    unconditional: bool,
+    /// Number of jumps found so far, whether to sections or ending the dialogue
+    jumps: usize,
+    /// Whether the current step follows an unconditional jump, and so can never play
+    unreachable: bool,
+}
+
+/// Checks how dialogue flows between sections and steps, reporting:
+/// - Jumps and bounces to sections which don't exist
+/// - Sections which can never be reached from the entry section
+/// - Sections which can never lead to the dialogue ending
+/// - Sections which bounce into each other forever
+/// - Steps which follow an unconditional jump, and so can never play
+pub(super) fn check_flow(analyzer: &mut Analyzer, entry: Option<&str>) {
+    let dialogue = analyzer.dialogue;
+    let sections = &dialogue.sections;
+
+    // The first of any sections sharing a name is the one navigated to
+    let mut section_indices = HashMap::new();
+    for (index, section) in sections.iter().enumerate() {
+        section_indices
+            .entry(section.name.as_str())
+            .or_insert(index);
+    }
+
+    let flows = sections
+        .iter()
+        .map(|section| {
+            let mut checker = FlowChecker {
+                analyzer: &mut *analyzer,
+                section_indices: &section_indices,
+                flow: SectionFlow::default(),
+                unconditional: true,
+                jumps: 0,
+                unreachable: false,
+            };
+
+            checker.flow.runs_on = checker.check_block(&section.steps);
+            checker.flow
+        })
+        .collect::<Vec<_>>();
+
+    let entry_index = match entry {
+        Some(entry) => match section_indices.get(entry) {
+            Some(&index) => Some(index),
+            None => {
+                analyzer.report(
+                    DiagnosticCode::UndefinedSection,
+                    format!("Entry section not found [{entry}]"),
+                    None,
+                );
+                None
+            }
+        },
+        None => (!sections.is_empty()).then_some(0),
+    };
+
+    let reachable = find_reachable_sections(&flows, entry_index);
+    let can_end = find_ending_sections(&flows);
+
+    for (index, section) in sections.iter().enumerate() {
+        if !reachable.contains(&index) {
+            analyzer
+                .report(
+                    DiagnosticCode::UnreachableSection,
+                    format!("Section can never be reached [{}]", section.name),
+                    section.location.as_ref(),
+                )
+                .with_suggestion(format!(
+                    "Navigate to the section: `{} {}`",
+                    syntax::navigation::JUMP,
+                    section.name
+                ));
+        } else if !can_end[index] {
+            analyzer
+                .report(
+                    DiagnosticCode::EndlessSection,
+                    format!(
+                        "Section never leads to the dialogue ending [{}]",
+                        section.name
+                    ),
+                    section.location.as_ref(),
+                )
+                .with_suggestion(format!(
+                    "End the dialogue from the section, or one it leads to: `{} END`",
+                    syntax::navigation::JUMP
+                ));
+        }
+    }
+
+    for cycle in find_bounce_cycles(&flows) {
+        let (_, location) = flows[cycle[0]]
+            .unconditional_bounces
+            .iter()
+            .find(|(target, _)| *target == cycle[1])
+            .expect("Cycle should follow unconditional bounces");
+
+        let names = cycle
+            .iter()
+            .map(|&index| sections[index].name.as_str())
+            .collect::<Vec<_>>();
+
+        analyzer
+            .report(
+                DiagnosticCode::BounceCycle,
+                format!(
+                    "Section bounces into itself forever [{}]",
+                    names.join(" -> ")
+                ),
+                location.as_ref(),
+            )
+            .with_suggestion("Only bounce under a condition, or after navigating elsewhere");
+    }
+}
+
+impl FlowChecker<'_, '_> {
+    /// Checks a block of steps, returning whether it can run out of steps rather than navigating
+    /// away.
+    fn check_block(&mut self, steps: &[DialogueStep]) -> bool {
+        let enclosing_unreachable = self.unreachable;
+        let mut runs_out = true;
+
+        for step in steps {
+            if let DialogueStep::Comment { .. } = step {
+                continue;
+            }
+
+            if !runs_out && !self.unreachable {
+                self.analyzer
+                    .report(
+                        DiagnosticCode::UnreachableStep,
+                        "Step can never play, as it follows an unconditional jump",
+                        step.location(),
+                    )
+                    .with_suggestion("Remove the step, or only jump under a condition");
+
+                self.unreachable = true;
+            }
+
+            let jumps = self.jumps;
+            runs_out &= self.check_step(step);
+
+            // Steps may not play once a jump may have been taken
+            if self.jumps > jumps {
+                self.unconditional = false;
+            }
+        }
+
+        self.unreachable = enclosing_unreachable;
+        runs_out
+    }
+
+    /// Checks a block nested in a step, which may not play every time its step does.
+    fn check_nested_block(&mut self, steps: &[DialogueStep]) -> bool {
+        let unconditional = std::mem::replace(&mut self.unconditional, false);
+        let runs_out = self.check_block(steps);
+
+        self.unconditional = unconditional;
+        runs_out
+    }
+
+    /// Checks a step, returning whether playback can continue to the step after it.
+    fn check_step(&mut self, step: &DialogueStep) -> bool {
+        match step {
+            DialogueStep::SectionJump { target, location } => {
+                if let Some(target) = self.resolve_section(target, location.as_ref()) {
+                    self.flow.jump_targets.push(target);
+                }
+
+                self.jumps += 1;
+                false
+            }
+
+            DialogueStep::SectionBounce { target, location } => {
+                if let Some(target) = self.resolve_section(target, location.as_ref()) {
+                    self.flow.bounce_targets.push(target);
+
+                    if self.unconditional {
+                        self.flow
+                            .unconditional_bounces
+                            .push((target, location.clone()));
+                    }
+                }
+
+                true
+            }
+
+            DialogueStep::EndJump { .. } | DialogueStep::TerminateJump { .. } => {
+                if !self.unreachable {
+                    self.flow.ends = true;
+                    self.flow.terminates |= matches!(step, DialogueStep::TerminateJump { .. });
+                }
+
+                self.jumps += 1;
+                false
+            }
+
+            DialogueStep::Conditional {
+                then_steps,
+                else_steps,
+                ..
+            } => {
+                let then_runs_out = self.check_nested_block(then_steps);
+                let else_runs_out = self.check_nested_block(else_steps);
+
+                then_runs_out || else_runs_out
+            }
+
+            DialogueStep::Page { lines, .. } => {
+                let responses = lines
+                    .iter()
+                    .filter_map(|line| match line {
+                        DialogueLine::Response { pages, .. } => Some(pages),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+
+                if responses.is_empty() {
+                    return true;
+                }
+
+                // Every response is checked, rather than stopping at the first which runs out
+                let mut runs_out = false;
+                for steps in responses {
+                    runs_out |= self.check_nested_block(steps);
+                }
+
+                runs_out
+            }
+
+            DialogueStep::Repeat { count, steps, .. } => {
+                let body_runs_out = self.check_nested_block(steps);
+                *count == 0 || body_runs_out
+            }
+
+            // Loops may not play at all, so always continue after
+            DialogueStep::While { steps, .. } | DialogueStep::Each { steps, .. } => {
+                self.check_nested_block(steps);
+                true
+            }
+
+            _ => true,
+        }
+    }
+
+    /// Gets the index of the named section, reporting it if it doesn't exist.
+    ///
+    /// Sections navigated to by steps which can never play are treated as not being navigated to.
+    fn resolve_section(&mut self, name: &str, location: Option<&SourceLocation>) -> Option<usize> {
+        let Some(&index) = self.section_indices.get(name) else {
+            self.analyzer
+                .report(
+                    DiagnosticCode::UndefinedSection,
+                    format!("Section not found [{name}]"),
+                    location,
+                )
+                .with_suggestion(format!(
+                    "Define the section: `{} {name}`",
+                    syntax::prefixes::SECTION
+                ));
+            return None;
+        };
+
+        (!self.unreachable).then_some(index)
+    }
+}
+
+/// Finds the sections which can be reached from the entry section.
+fn find_reachable_sections(flows: &[SectionFlow], entry: Option<usize>) -> HashSet<usize> {
+    let mut reachable = HashSet::new();
+    let mut pending = entry.into_iter().collect::<Vec<_>>();
+
+    while let Some(index) = pending.pop() {
+        if !reachable.insert(index) {
+            continue;
+        }
+
+        let flow = &flows[index];
+        pending.extend(&flow.jump_targets);
+        pending.extend(&flow.bounce_targets);
+
+        if flow.runs_on && index + 1 < flows.len() {
+            pending.push(index + 1);
+        }
+    }
+
+    reachable
+}
+
+/// Finds whether each section can lead to the dialogue ending, whether by ending it directly,
+/// navigating to a section which does, or running out of steps after the last section.
+///
+/// Sections bounced to return to the section which bounced when they end, so bounces only lead
+/// to the dialogue ending when the section bounced to can terminate it.
+fn find_ending_sections(flows: &[SectionFlow]) -> Vec<bool> {
+    let can_terminate = spread_through_sections(flows, |index, flow, can_terminate| {
+        flow.terminates
+            || (flow.runs_on && can_terminate.get(index + 1).copied().unwrap_or(false))
+            || flow
+                .jump_targets
+                .iter()
+                .chain(&flow.bounce_targets)
+                .any(|&target| can_terminate[target])
+    });
+
+    spread_through_sections(flows, |index, flow, can_end| {
+        flow.ends
+            || (flow.runs_on && can_end.get(index + 1).copied().unwrap_or(true))
+            || flow.jump_targets.iter().any(|&target| can_end[target])
+            || flow
+                .bounce_targets
+                .iter()
+                .any(|&target| can_terminate[target])
+    })
+}
+
+/// Finds which sections meet a condition depending on the sections they lead to, spreading
+/// outwards from those which meet it directly until nothing changes.
+fn spread_through_sections(
+    flows: &[SectionFlow],
+    meets: impl Fn(usize, &SectionFlow, &[bool]) -> bool,
+) -> Vec<bool> {
+    let mut met = vec![false; flows.len()];
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for (index, flow) in flows.iter().enumerate() {
+            if !met[index] && meets(index, flow, &met) {
+                met[index] = true;
+                changed = true;
+            }
+        }
+    }
+
+    met
+}
+
+/// Finds cycles of sections which bounce into each other every time they play, so never return.
+///
+/// Each cycle is given as the sections in it, in the order they bounce, ending with the section
+/// it started from.
+fn find_bounce_cycles(flows: &[SectionFlow]) -> Vec<Vec<usize>> {
+    let mut search = CycleSearch {
+        flows,
+        path: Vec::new(),
+        visited: HashSet::new(),
+        found: HashSet::new(),
+        cycles: Vec::new(),
+    };
+
+    for start in 0..flows.len() {
+        search.visit(start);
+    }
+
+    search.cycles
+}
+
+/// Depth-first search for cycles of unconditional bounces.
+struct CycleSearch<'a> {
+    flows: &'a [SectionFlow],
+    /// Sections bounced through to reach the current section
+    path: Vec<usize>,
+    visited: HashSet<usize>,
+    /// Sections of each cycle found, sorted, as the same cycle is found from each of them
+    found: HashSet<Vec<usize>>,
+    cycles: Vec<Vec<usize>>,
+}
+
+impl CycleSearch<'_> {
+    fn visit(&mut self, index: usize) {
+        if !self.visited.insert(index) {
+            return;
+        }
+
+        self.path.push(index);
+
+        for &(target, _) in &self.flows[index].unconditional_bounces {
+            match self.path.iter().position(|&step| step == target) {
+                Some(cycle_start) => {
+                    let mut cycle = self.path[cycle_start..].to_vec();
+
+                    let mut members = cycle.clone();
+                    members.sort_unstable();
+
+                    if self.found.insert(members) {
+                        cycle.push(target);
+                        self.cycles.push(cycle);
+                    }
+                }
+                None => self.visit(target),
+            }
+        }
+
+        self.path.pop();
+    }
+}
@@ -0,0 +1,126 @@
+//! Static analysis of parsed dialogue, finding problems which would otherwise only show up while
+//! it plays.
+
+mod flow;
+#[cfg(test)]
+mod tests;
+mod typing;
+mod usage;
+
+use std::collections::HashMap;
+
+use crate::{
+    Diagnostic, DiagnosticCode, Dialogue, LintSuppressions, SourceLocation, SourceMap, SourceSpan,
+};
+
+/// Analyzes a dialogue for problems with how it plays, such as navigating to sections which don't
+/// exist, steps which can never be reached, values used as the wrong type or definitions which are
+/// never used.
+///
+/// Sources are given by path, as in [`ProjectResult::sources`](crate::ProjectResult::sources),
+/// with source parsed from a string given under an empty path. They're used to point diagnostics
+/// at the lines they're about, leaving out those allowed by suppression comments, see
+/// [`LintSuppressions`]. Dialogue is analyzed as starting from the entry section, or the
+/// first section if none is given.
+///
+/// # Example
+/// ```
+/// use std::collections::HashMap;
+/// use dialogue_syntax::{DiagnosticCode, analyze, parse};
+///
+/// let source = "# intro\nHello!\n=> outro".to_string();
+/// let dialogue = parse(source.clone()).dialogue;
+///
+/// let diagnostics = analyze(&dialogue, &HashMap::from([(String::new(), source)]), None);
+/// assert_eq!(diagnostics[0].code, DiagnosticCode::UndefinedSection);
+/// ```
+pub fn analyze(
+    dialogue: &Dialogue,
+    sources: &HashMap<String, String>,
+    entry: Option<&str>,
+) -> Vec<Diagnostic> {
+    let mut analyzer = Analyzer::new(dialogue, sources);
+
+    flow::check_flow(&mut analyzer, entry);
+    typing::check_types(&mut analyzer);
+    usage::check_usage(&mut analyzer);
+
+    // Diagnostics allowed by suppression comments are left out
+    let mut diagnostics = analyzer.diagnostics;
+    for (path, source) in sources {
+        let suppressions = LintSuppressions::find(source);
+
+        diagnostics.retain(|diagnostic| {
+            diagnostic.file.as_deref().unwrap_or_default() != path
+                || !suppressions.allows(diagnostic)
+        });
+    }
+
+    diagnostics
+}
+
+/// State of an analysis in progress.
+struct Analyzer<'a> {
+    dialogue: &'a Dialogue,
+    /// Source of each file, by path
+    source_maps: HashMap<&'a str, SourceMap<'a>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(dialogue: &'a Dialogue, sources: &'a HashMap<String, String>) -> Self {
+        let source_maps = sources
+            .iter()
+            .map(|(path, source)| (path.as_str(), SourceMap::new(source)))
+            .collect();
+
+        Self {
+            dialogue,
+            source_maps,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Gets the span of the line at the given location, falling back to just its line and column
+    /// if its source isn't known.
+    fn span_of(&self, location: Option<&SourceLocation>) -> SourceSpan {
+        let Some(location) = location else {
+            return SourceSpan::default();
+        };
+
+        let path = location.file.as_deref().unwrap_or_default();
+
+        match self.source_maps.get(path) {
+            Some(source_map) => source_map.line_span(location.line),
+            None => SourceSpan {
+                line: location.line,
+                column: location.column,
+                ..SourceSpan::default()
+            },
+        }
+    }
+
+    /// Reports a diagnostic spanning the line at the given location.
+    fn report(
+        &mut self,
+        code: DiagnosticCode,
+        message: impl Into<String>,
+        location: Option<&SourceLocation>,
+    ) -> &mut Diagnostic {
+        let mut diagnostic = Diagnostic::new(code, message, self.span_of(location));
+        diagnostic.file = location.and_then(|location| location.file.clone());
+
+        self.diagnostics.push(diagnostic);
+        self.diagnostics.last_mut().unwrap()
+    }
+
+    /// Adds the line at the given location to the diagnostic reported last, with a note on how it
+    /// relates.
+    fn relate(&mut self, location: &SourceLocation, message: impl Into<String>) {
+        let span = self.span_of(Some(location));
+
+        if let Some(diagnostic) = self.diagnostics.last_mut() {
+            diagnostic.with_related_in(location.file.clone(), span, message);
+        }
+    }
+}
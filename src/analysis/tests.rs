@@ -0,0 +1,483 @@
+#[cfg(test)]
+use crate::*;
+
+#[cfg(test)]
+use std::collections::HashMap;
+
+/// Helper function for tests - analyzes the given dialogue, ignoring any parse diagnostics
+fn analyze_test_helper(input: &str, entry: Option<&str>) -> Vec<Diagnostic> {
+    let dialogue = parse(input.to_string()).dialogue;
+    let sources = HashMap::from([(String::new(), input.to_string())]);
+
+    analyze(&dialogue, &sources, entry)
+}
+
+/// Helper function for tests - gets the code, line and message of each diagnostic
+fn summarize(diagnostics: &[Diagnostic]) -> Vec<(DiagnosticCode, usize, &str)> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.code,
+                diagnostic.span.line,
+                diagnostic.message.as_str(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_flow_of_valid_dialogue() {
+    let input = r"
+# intro
+Hello
+=><= aside
+[if=true]
+  => outro
+~
+- Stay
+  Staying
+- Leave
+  => END
+
+# aside
+Psst
+
+# outro
+// Comments can follow a jump
+=> TERMINATE
+// Like this";
+
+    assert_eq!(analyze_test_helper(input, None), vec![]);
+}
+
+#[test]
+fn test_flow_of_steps() {
+    let input = r"
+# intro
+=> missing
+Never said
+Nor this
+[if=true]
+  => END
+~
+- Leave
+  =><= nowhere
+  => END
+  Never said either
+- Stay
+  => intro
+After the choice";
+
+    let diagnostics = analyze_test_helper(input, None);
+
+    assert_eq!(
+        summarize(&diagnostics),
+        vec![
+            (
+                DiagnosticCode::UndefinedSection,
+                3,
+                "Section not found [missing]"
+            ),
+            (
+                DiagnosticCode::UnreachableStep,
+                4,
+                "Step can never play, as it follows an unconditional jump"
+            ),
+            (
+                DiagnosticCode::UndefinedSection,
+                10,
+                "Section not found [nowhere]"
+            ),
+            (
+                DiagnosticCode::EndlessSection,
+                2,
+                "Section never leads to the dialogue ending [intro]"
+            ),
+        ]
+    );
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(
+        diagnostics[0].suggestions,
+        vec!["Define the section: `# missing`"]
+    );
+}
+
+#[test]
+fn test_flow_between_sections() {
+    let input = r"
+# intro
+- Shop
+  => shop
+- Leave
+  => outro
+
+# shop
+Welcome
+=> intro
+
+# outro
+=> intro
+
+# secret
+=> END";
+
+    // Every section only leads back to the intro, which never ends
+    assert_eq!(
+        summarize(&analyze_test_helper(input, None)),
+        vec![
+            (
+                DiagnosticCode::EndlessSection,
+                2,
+                "Section never leads to the dialogue ending [intro]"
+            ),
+            (
+                DiagnosticCode::EndlessSection,
+                8,
+                "Section never leads to the dialogue ending [shop]"
+            ),
+            (
+                DiagnosticCode::EndlessSection,
+                12,
+                "Section never leads to the dialogue ending [outro]"
+            ),
+            (
+                DiagnosticCode::UnreachableSection,
+                15,
+                "Section can never be reached [secret]"
+            ),
+        ]
+    );
+
+    // Sections before the entry are only reached by navigating to them
+    assert_eq!(
+        summarize(&analyze_test_helper(input, Some("secret"))),
+        vec![
+            (
+                DiagnosticCode::UnreachableSection,
+                2,
+                "Section can never be reached [intro]"
+            ),
+            (
+                DiagnosticCode::UnreachableSection,
+                8,
+                "Section can never be reached [shop]"
+            ),
+            (
+                DiagnosticCode::UnreachableSection,
+                12,
+                "Section can never be reached [outro]"
+            ),
+        ]
+    );
+
+    assert_eq!(
+        summarize(&analyze_test_helper(input, Some("missing")))[0],
+        (
+            DiagnosticCode::UndefinedSection,
+            0,
+            "Entry section not found [missing]"
+        )
+    );
+}
+
+#[test]
+fn test_flow_of_bounces() {
+    let input = r"
+# intro
+=><= ping
+=><= pang
+=> END
+
+# ping
+Ping
+=><= pong
+=> END
+
+# pong
+[if=true]
+  Pong
+~
+=><= ping
+=> END
+
+# pang
+[if=true]
+  => END
+~
+=><= pang
+=> END
+
+# echo
+=><= echo
+=> END";
+
+    let diagnostics = analyze_test_helper(input, None);
+
+    // Bounces after a jump may not happen, so only unconditional ones make a cycle
+    assert_eq!(
+        summarize(&diagnostics),
+        vec![
+            (
+                DiagnosticCode::UnreachableSection,
+                26,
+                "Section can never be reached [echo]"
+            ),
+            (
+                DiagnosticCode::BounceCycle,
+                9,
+                "Section bounces into itself forever [ping -> pong -> ping]"
+            ),
+            (
+                DiagnosticCode::BounceCycle,
+                27,
+                "Section bounces into itself forever [echo -> echo]"
+            ),
+        ]
+    );
+
+    // Sections bounced to return when they end, so only lead to the dialogue ending if they can
+    // terminate it
+    let input = r"
+# loop
+Hi
+=><= aside
+=> loop
+
+# aside
+Aside
+=> END";
+
+    let diagnostics = analyze_test_helper(input, None);
+
+    assert_eq!(
+        summarize(&diagnostics),
+        vec![(
+            DiagnosticCode::EndlessSection,
+            2,
+            "Section never leads to the dialogue ending [loop]"
+        )]
+    );
+
+    let input = r"
+# loop
+=><= exit
+=> loop
+
+# exit
+[if=true]
+  => TERMINATE
+~
+=> END";
+
+    assert!(analyze_test_helper(input, None).is_empty());
+}
+
+#[test]
+fn test_types_of_assignments() {
+    let input = r"
+$gold: 10
+$armed: false
+!greet: hello
+
+# intro
+$gold = $gold + 5
+$gold = lots
+$armed = !greet()
+=> END";
+
+    let diagnostics = analyze_test_helper(input, None);
+
+    assert_eq!(
+        summarize(&diagnostics),
+        vec![
+            (
+                DiagnosticCode::TypeMismatch,
+                8,
+                "Assignment changes the variable from number to text [gold]"
+            ),
+            (
+                DiagnosticCode::TypeMismatch,
+                9,
+                "Assignment changes the variable from boolean to text [armed]"
+            ),
+        ]
+    );
+
+    let related = |diagnostic: &Diagnostic| {
+        diagnostic
+            .related
+            .iter()
+            .map(|related| (related.span.line, related.message.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        related(&diagnostics[0]),
+        vec![(2, "variable defined here as number".to_string())]
+    );
+    assert_eq!(
+        related(&diagnostics[1]),
+        vec![
+            (3, "variable defined here as boolean".to_string()),
+            (4, "function defined here with a text result".to_string()),
+        ]
+    );
+    assert_eq!(
+        diagnostics[0].suggestions,
+        vec!["Assign a number value, or define the variable as text"]
+    );
+}
+
+#[test]
+fn test_types_of_expressions() {
+    let input = r"
+$gold: 10
+$name: Oscar
+$items: [sword, shield]
+
+@Oscar
+age: 26
+
+# intro
+$name = $name + 1
+[if=$gold == Oscar]
+  @Oscar: Hmm
+~
+[if=@oscar.age > $name]
+  Hmm
+~
+$gold = $items * 2
+$gold = - $name
+~ EACH $items as $item
+  ~ EACH $item as $letter
+    {$letter}
+  ~
+~
+=> END";
+
+    let diagnostics = analyze_test_helper(input, None);
+
+    assert_eq!(
+        summarize(&diagnostics),
+        vec![
+            (
+                DiagnosticCode::IncompatibleTypes,
+                11,
+                "Values of different types are never equal [number == text]"
+            ),
+            (
+                DiagnosticCode::IncompatibleTypes,
+                14,
+                "Only two numbers or two pieces of text can be ordered [number > text]"
+            ),
+            (
+                DiagnosticCode::ArrayMisuse,
+                17,
+                "Arrays cannot be used in arithmetic [array * number]"
+            ),
+            (
+                DiagnosticCode::IncompatibleTypes,
+                18,
+                "Only numbers can be negated [-text]"
+            ),
+            (
+                DiagnosticCode::ArrayMisuse,
+                20,
+                "Loop item is text, so cannot be looped over [item]"
+            ),
+        ]
+    );
+
+    // Both sides of a comparison point at their definitions
+    let related_lines = diagnostics[1]
+        .related
+        .iter()
+        .map(|related| related.span.line)
+        .collect::<Vec<_>>();
+    assert_eq!(related_lines, vec![6, 3]);
+}
+
+#[test]
+fn test_usage_of_definitions() {
+    let input = r"
+@Oscar
+@Robin
+$gold: 10
+$silver: 5
+$items: [sword]
+$quiet: true
+!give
+!take
+
+# intro
+@oscar: You have {$gold} gold
+$silver = 0
+~ EACH $items as $item
+  {$item}
+~
+=><= !give
+=> shop
+
+# shop
+Welcome
+=> END
+
+# shop
+Welcome back
+=> END";
+
+    let diagnostics = analyze_test_helper(input, None);
+
+    assert_eq!(
+        summarize(&diagnostics),
+        vec![
+            (
+                DiagnosticCode::UnreachableSection,
+                24,
+                "Section can never be reached [shop]"
+            ),
+            (
+                DiagnosticCode::DuplicateSection,
+                24,
+                "Section name is already used [shop]"
+            ),
+            (DiagnosticCode::SilentActor, 3, "Actor never speaks [robin]"),
+            (
+                DiagnosticCode::UnusedVariable,
+                7,
+                "Variable is never used [quiet]"
+            ),
+            (
+                DiagnosticCode::UnusedFunction,
+                9,
+                "Function is never called [take]"
+            ),
+        ]
+    );
+
+    assert_eq!(diagnostics[1].related[0].span.line, 20);
+    assert_eq!(diagnostics[1].related[0].message, "first used here");
+}
+
+#[test]
+fn test_suppressed_diagnostics() {
+    let input = r"
+// lex-allow: unused-variable
+$gold: 10
+$silver: 5
+
+# intro
+=> END
+// lex-allow: unreachable-step
+Never said";
+
+    assert_eq!(
+        summarize(&analyze_test_helper(input, None)),
+        vec![(
+            DiagnosticCode::UnusedVariable,
+            4,
+            "Variable is never used [silver]"
+        )]
+    );
+}
@@ -0,0 +1,429 @@
+use super::Analyzer;
+use crate::{
+    BinaryOperator, DiagnosticCode, DialogueExpression, DialogueLine, DialogueStep, DialogueValue,
+    SourceLocation, UnaryOperator,
+};
+
+/// Type of a value, as known before the dialogue plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ValueType {
+    Text,
+    Number,
+    Boolean,
+    Array,
+}
+
+impl ValueType {
+    fn of(value: &DialogueValue) -> Self {
+        match value {
+            DialogueValue::Text(_) => ValueType::Text,
+            DialogueValue::Number(_) => ValueType::Number,
+            DialogueValue::Boolean(_) => ValueType::Boolean,
+            DialogueValue::Array(_) => ValueType::Array,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ValueType::Text => "text",
+            ValueType::Number => "number",
+            ValueType::Boolean => "boolean",
+            ValueType::Array => "array",
+        }
+    }
+}
+
+/// State of the type checks in progress.
+struct TypeChecker<'a, 'b> {
+    analyzer: &'b mut Analyzer<'a>,
+    /// Items of the loops enclosing the current step, which are always text
+    loop_items: Vec<&'a str>,
+}
+
+/// Checks that values are used as the types they're defined as, reporting:
+/// - Assignments which change the type of a variable, including from function results
+/// - Comparisons and arithmetic between values of incompatible types
+/// - Arrays used in arithmetic or ordering, and loops over values which aren't arrays
+///
+/// Variables, actor properties and function results take the types of the values they're defined
+/// with, and loop items are always text. Values of unknown type, such as undefined variables,
+/// are left to the parser to report.
+pub(super) fn check_types(analyzer: &mut Analyzer) {
+    let dialogue = analyzer.dialogue;
+
+    let mut checker = TypeChecker {
+        analyzer,
+        loop_items: Vec::new(),
+    };
+
+    for section in &dialogue.sections {
+        checker.check_block(&section.steps);
+    }
+}
+
+impl<'a> TypeChecker<'a, '_> {
+    fn check_block(&mut self, steps: &'a [DialogueStep]) {
+        for step in steps {
+            self.check_step(step);
+        }
+    }
+
+    fn check_step(&mut self, step: &'a DialogueStep) {
+        match step {
+            DialogueStep::VariableAssign {
+                name,
+                value,
+                location,
+            } => {
+                let assigned = self.check_expression(value, location.as_ref());
+
+                if let Some(assigned) = assigned
+                    && let Some(defined) = self.variable_type(name)
+                    && assigned != defined
+                {
+                    self.report_type_change(name, defined, assigned, location.as_ref());
+                }
+            }
+
+            DialogueStep::FunctionCall {
+                name,
+                result: Some(variable),
+                location,
+                ..
+            } => {
+                let function = self.analyzer.dialogue.functions.get(name);
+                let returned = function
+                    .and_then(|function| function.result.as_ref())
+                    .map(ValueType::of);
+
+                if let Some(function) = function
+                    && let Some(returned) = returned
+                    && let Some(defined) = self.variable_type(variable)
+                    && returned != defined
+                {
+                    self.report_type_change(variable, defined, returned, location.as_ref());
+
+                    if let Some(function_location) = &function.location {
+                        self.analyzer.relate(
+                            function_location,
+                            format!("function defined here with a {} result", returned.name()),
+                        );
+                    }
+                }
+            }
+
+            DialogueStep::Conditional {
+                condition,
+                then_steps,
+                else_steps,
+                location,
+            } => {
+                self.check_expression(condition, location.as_ref());
+                self.check_block(then_steps);
+                self.check_block(else_steps);
+            }
+
+            DialogueStep::While {
+                condition,
+                steps,
+                location,
+            } => {
+                self.check_expression(condition, location.as_ref());
+                self.check_block(steps);
+            }
+
+            DialogueStep::Repeat { steps, .. } => self.check_block(steps),
+
+            DialogueStep::Each {
+                array,
+                item,
+                steps,
+                location,
+            } => {
+                // Loops over defined variables which aren't arrays are reported by the parser
+                if self.loop_items.contains(&array.as_str()) {
+                    self.analyzer
+                        .report(
+                            DiagnosticCode::ArrayMisuse,
+                            format!("Loop item is text, so cannot be looped over [{array}]"),
+                            location.as_ref(),
+                        )
+                        .with_suggestion("Loop over a variable defined as an array");
+                }
+
+                self.loop_items.push(item);
+                self.check_block(steps);
+                self.loop_items.pop();
+            }
+
+            DialogueStep::Page { lines, .. } => {
+                for line in lines {
+                    if let DialogueLine::Response { pages, .. } = line {
+                        self.check_block(pages);
+                    }
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Checks an expression for operations on incompatible types, returning the type it evaluates
+    /// to, if known.
+    fn check_expression(
+        &mut self,
+        expression: &DialogueExpression,
+        location: Option<&SourceLocation>,
+    ) -> Option<ValueType> {
+        match expression {
+            DialogueExpression::Value(value) => Some(ValueType::of(value)),
+
+            DialogueExpression::Variable(name) => self.variable_type(name),
+
+            DialogueExpression::ActorProperty { actor, property } => self
+                .analyzer
+                .dialogue
+                .actors
+                .get(actor)
+                .and_then(|actor| actor.properties.get(property))
+                .map(ValueType::of),
+
+            DialogueExpression::Unary {
+                operator: UnaryOperator::Not,
+                operand,
+            } => {
+                self.check_expression(operand, location);
+                Some(ValueType::Boolean)
+            }
+
+            DialogueExpression::Unary {
+                operator: UnaryOperator::Negate,
+                operand,
+            } => {
+                if let Some(operand_type) = self.check_expression(operand, location)
+                    && operand_type != ValueType::Number
+                {
+                    let code = match operand_type {
+                        ValueType::Array => DiagnosticCode::ArrayMisuse,
+                        _ => DiagnosticCode::IncompatibleTypes,
+                    };
+
+                    self.analyzer.report(
+                        code,
+                        format!(
+                            "Only numbers can be negated [{}{}]",
+                            UnaryOperator::Negate.symbol(),
+                            operand_type.name()
+                        ),
+                        location,
+                    );
+                    self.relate_definitions(&[operand]);
+                }
+
+                Some(ValueType::Number)
+            }
+
+            DialogueExpression::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left_type = self.check_expression(left, location);
+                let right_type = self.check_expression(right, location);
+
+                let (Some(left_type), Some(right_type)) = (left_type, right_type) else {
+                    return result_type(*operator, None);
+                };
+
+                if let Some((code, problem)) =
+                    find_operand_problem(left_type, *operator, right_type)
+                {
+                    self.analyzer.report(
+                        code,
+                        format!(
+                            "{problem} [{} {} {}]",
+                            left_type.name(),
+                            operator.symbol(),
+                            right_type.name()
+                        ),
+                        location,
+                    );
+                    self.relate_definitions(&[left, right]);
+
+                    return result_type(*operator, None);
+                }
+
+                result_type(*operator, Some((left_type, right_type)))
+            }
+        }
+    }
+
+    /// Gets the type of the named variable, if it's defined or a loop item.
+    fn variable_type(&self, name: &str) -> Option<ValueType> {
+        if self.loop_items.contains(&name) {
+            return Some(ValueType::Text);
+        }
+
+        self.analyzer
+            .dialogue
+            .variables
+            .get(name)
+            .map(ValueType::of)
+    }
+
+    /// Reports a variable being given a value of a different type to the one it's defined with.
+    fn report_type_change(
+        &mut self,
+        name: &str,
+        defined: ValueType,
+        assigned: ValueType,
+        location: Option<&SourceLocation>,
+    ) {
+        self.analyzer
+            .report(
+                DiagnosticCode::TypeMismatch,
+                format!(
+                    "Assignment changes the variable from {} to {} [{name}]",
+                    defined.name(),
+                    assigned.name()
+                ),
+                location,
+            )
+            .with_suggestion(format!(
+                "Assign a {} value, or define the variable as {}",
+                defined.name(),
+                assigned.name()
+            ));
+
+        self.relate_variable(name, defined);
+    }
+
+    /// Points the diagnostic reported last at where any variables or actor properties among the
+    /// given operands are defined.
+    fn relate_definitions(&mut self, operands: &[&DialogueExpression]) {
+        let dialogue = self.analyzer.dialogue;
+
+        for operand in operands {
+            match operand {
+                DialogueExpression::Variable(name) => {
+                    if let Some(defined) = self.variable_type(name) {
+                        self.relate_variable(name, defined);
+                    }
+                }
+
+                DialogueExpression::ActorProperty { actor, property } => {
+                    let Some(actor) = dialogue.actors.get(actor) else {
+                        continue;
+                    };
+
+                    if let Some(location) = &actor.location
+                        && let Some(value) = actor.properties.get(property)
+                    {
+                        self.analyzer.relate(
+                            location,
+                            format!(
+                                "actor defined here, with {property} as {}",
+                                ValueType::of(value).name()
+                            ),
+                        );
+                    }
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    /// Points the diagnostic reported last at where the named variable is defined, unless it's a
+    /// loop item.
+    fn relate_variable(&mut self, name: &str, defined: ValueType) {
+        if self.loop_items.contains(&name) {
+            return;
+        }
+
+        if let Some(location) = self.analyzer.dialogue.variable_locations.get(name) {
+            self.analyzer.relate(
+                location,
+                format!("variable defined here as {}", defined.name()),
+            );
+        }
+    }
+}
+
+/// Finds why a binary operator can't be applied to operands of the given types, if it can't,
+/// along with the code to report it under.
+///
+/// Mirrors how operators are applied during playback, where adding text to any other value
+/// concatenates them.
+fn find_operand_problem(
+    left: ValueType,
+    operator: BinaryOperator,
+    right: ValueType,
+) -> Option<(DiagnosticCode, &'static str)> {
+    let has_array = left == ValueType::Array || right == ValueType::Array;
+
+    match operator {
+        BinaryOperator::And | BinaryOperator::Or => None,
+
+        BinaryOperator::Equal | BinaryOperator::NotEqual => (left != right).then_some((
+            DiagnosticCode::IncompatibleTypes,
+            "Values of different types are never equal",
+        )),
+
+        BinaryOperator::Less
+        | BinaryOperator::LessOrEqual
+        | BinaryOperator::Greater
+        | BinaryOperator::GreaterOrEqual => {
+            if has_array {
+                Some((DiagnosticCode::ArrayMisuse, "Arrays cannot be ordered"))
+            } else if left != right || left == ValueType::Boolean {
+                Some((
+                    DiagnosticCode::IncompatibleTypes,
+                    "Only two numbers or two pieces of text can be ordered",
+                ))
+            } else {
+                None
+            }
+        }
+
+        BinaryOperator::Add if left == ValueType::Text || right == ValueType::Text => None,
+
+        BinaryOperator::Add
+        | BinaryOperator::Subtract
+        | BinaryOperator::Multiply
+        | BinaryOperator::Divide
+        | BinaryOperator::Remainder => {
+            if has_array {
+                Some((
+                    DiagnosticCode::ArrayMisuse,
+                    "Arrays cannot be used in arithmetic",
+                ))
+            } else if left != ValueType::Number || right != ValueType::Number {
+                Some((
+                    DiagnosticCode::IncompatibleTypes,
+                    "Only numbers can be used in arithmetic",
+                ))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Gets the type a binary operator evaluates to, given the types of its operands if known.
+fn result_type(
+    operator: BinaryOperator,
+    operands: Option<(ValueType, ValueType)>,
+) -> Option<ValueType> {
+    match operator {
+        BinaryOperator::Add => match operands? {
+            (ValueType::Text, _) | (_, ValueType::Text) => Some(ValueType::Text),
+            _ => Some(ValueType::Number),
+        },
+        BinaryOperator::Subtract
+        | BinaryOperator::Multiply
+        | BinaryOperator::Divide
+        | BinaryOperator::Remainder => Some(ValueType::Number),
+        _ => Some(ValueType::Boolean),
+    }
+}
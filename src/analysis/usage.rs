@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+
+use super::Analyzer;
+use crate::{
+    DiagnosticCode, DialogueExpression, DialogueLine, DialogueStep, DialogueText, SourceLocation,
+    TextSegment,
+};
+
+/// Definitions used by the steps of a dialogue.
+#[derive(Debug, Default)]
+struct Usage<'a> {
+    /// Actors speaking any line
+    speakers: HashSet<&'a str>,
+    /// Variables read or written
+    variables: HashSet<&'a str>,
+    /// Functions called
+    functions: HashSet<&'a str>,
+}
+
+/// Checks that sections are named uniquely, and that definitions are used, reporting:
+/// - Sections sharing a name with an earlier section, which can't be navigated to
+/// - Actors which never speak
+/// - Variables which are never read or written
+/// - Functions which are never called
+pub(super) fn check_usage(analyzer: &mut Analyzer) {
+    let dialogue = analyzer.dialogue;
+
+    let mut first_sections = HashMap::new();
+    for section in &dialogue.sections {
+        if section.name.is_empty() {
+            continue;
+        }
+
+        let Some(first) = first_sections.get(section.name.as_str()) else {
+            first_sections.insert(section.name.as_str(), section.location.as_ref());
+            continue;
+        };
+
+        analyzer
+            .report(
+                DiagnosticCode::DuplicateSection,
+                format!("Section name is already used [{}]", section.name),
+                section.location.as_ref(),
+            )
+            .with_suggestion("Rename one of the sections, as only the first can be navigated to");
+
+        if let Some(first) = first {
+            analyzer.relate(first, "first used here");
+        }
+    }
+
+    let mut usage = Usage::default();
+    for section in &dialogue.sections {
+        usage.collect_block(&section.steps);
+    }
+
+    let actors = dialogue
+        .actors
+        .iter()
+        .filter(|(id, _)| !usage.speakers.contains(id.as_str()))
+        .map(|(id, actor)| {
+            (
+                actor.location.as_ref(),
+                DiagnosticCode::SilentActor,
+                format!("Actor never speaks [{id}]"),
+            )
+        });
+
+    let variables = dialogue
+        .variables
+        .keys()
+        .filter(|name| !usage.variables.contains(name.as_str()))
+        .map(|name| {
+            (
+                dialogue.variable_locations.get(name),
+                DiagnosticCode::UnusedVariable,
+                format!("Variable is never used [{name}]"),
+            )
+        });
+
+    let functions = dialogue
+        .functions
+        .iter()
+        .filter(|(name, _)| !usage.functions.contains(name.as_str()))
+        .map(|(name, function)| {
+            (
+                function.location.as_ref(),
+                DiagnosticCode::UnusedFunction,
+                format!("Function is never called [{name}]"),
+            )
+        });
+
+    // Definitions are kept by name, so are reported in the order they're defined
+    let mut unused = actors.chain(variables).chain(functions).collect::<Vec<_>>();
+    unused.sort_by(|(a, _, a_message), (b, _, b_message)| {
+        definition_order(*a)
+            .cmp(&definition_order(*b))
+            .then_with(|| a_message.cmp(b_message))
+    });
+
+    for (location, code, message) in unused {
+        analyzer
+            .report(code, message, location)
+            .with_suggestion("Use the definition, or remove it");
+    }
+}
+
+/// Gets a key sorting definitions by where they're defined, with those of unknown location last.
+fn definition_order(location: Option<&SourceLocation>) -> (bool, Option<&str>, usize) {
+    match location {
+        Some(location) => (false, location.file.as_deref(), location.line),
+        None => (true, None, 0),
+    }
+}
+
+impl<'a> Usage<'a> {
+    fn collect_block(&mut self, steps: &'a [DialogueStep]) {
+        for step in steps {
+            self.collect_step(step);
+        }
+    }
+
+    fn collect_step(&mut self, step: &'a DialogueStep) {
+        match step {
+            DialogueStep::VariableAssign { name, value, .. } => {
+                self.variables.insert(name);
+                self.collect_expression(value);
+            }
+
+            DialogueStep::FunctionCall { name, result, .. } => {
+                self.functions.insert(name);
+
+                if let Some(result) = result {
+                    self.variables.insert(result);
+                }
+            }
+
+            DialogueStep::Conditional {
+                condition,
+                then_steps,
+                else_steps,
+                ..
+            } => {
+                self.collect_expression(condition);
+                self.collect_block(then_steps);
+                self.collect_block(else_steps);
+            }
+
+            DialogueStep::While {
+                condition, steps, ..
+            } => {
+                self.collect_expression(condition);
+                self.collect_block(steps);
+            }
+
+            DialogueStep::Each { array, steps, .. } => {
+                self.variables.insert(array);
+                self.collect_block(steps);
+            }
+
+            DialogueStep::Repeat { steps, .. } => self.collect_block(steps),
+
+            DialogueStep::Page { lines, .. } => {
+                for line in lines {
+                    match line {
+                        DialogueLine::Text { text, .. } => self.collect_text(text),
+                        DialogueLine::SpeakerText { speaker, text, .. } => {
+                            self.speakers.insert(speaker);
+                            self.collect_text(text);
+                        }
+                        DialogueLine::Response { text, pages, .. } => {
+                            self.collect_text(text);
+                            self.collect_block(pages);
+                        }
+                    }
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    fn collect_expression(&mut self, expression: &'a DialogueExpression) {
+        match expression {
+            DialogueExpression::Variable(name) => {
+                self.variables.insert(name);
+            }
+            DialogueExpression::Unary { operand, .. } => self.collect_expression(operand),
+            DialogueExpression::Binary { left, right, .. } => {
+                self.collect_expression(left);
+                self.collect_expression(right);
+            }
+            DialogueExpression::Value(_) | DialogueExpression::ActorProperty { .. } => {}
+        }
+    }
+
+    fn collect_text(&mut self, text: &'a DialogueText) {
+        for span in &text.spans {
+            if let TextSegment::Variable(name) = &span.content {
+                self.variables.insert(name);
+            }
+        }
+    }
+}
@@ -1,17 +1,39 @@
 //! Command line interface for the Lex dialogue syntax parser, converter and player.
 
-use crate::{parse, play};
-use base64::Engine;
 use clap::{Parser, Subcommand};
-use serde_pickle::SerOptions;
+use dialogue_syntax::{
+    CompiledDialogue, DiagnosticCode, Dialogue, DialogueRunner, FORMATS, LintLevel, LintRegistry,
+    MANIFEST_FILE_NAME, Manifest, ProjectResult, RunnerState, Severity, analyze, compile, convert,
+    load_project, play,
+};
+use serde::Serialize;
 
 /// Dialogue Syntax CLI
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
-    /// Path to the dialogue file
+    /// Path to the dialogue file, along with any files it includes (default: the sources of the
+    /// project manifest)
     #[arg(short, long)]
-    pub file: String,
+    pub file: Option<String>,
+
+    /// Path to the project manifest (default: the nearest `lex.toml` in the current directory or
+    /// its parents)
+    #[arg(short, long)]
+    pub manifest: Option<String>,
+
+    /// Leave out the diagnostics of a lint, overriding the project manifest (repeatable)
+    #[arg(short = 'A', long, value_name = "LINT", global = true)]
+    pub allow: Vec<DiagnosticCode>,
+
+    /// Report the diagnostics of a lint as warnings, overriding the project manifest (repeatable)
+    #[arg(short = 'W', long, value_name = "LINT", global = true)]
+    pub warn: Vec<DiagnosticCode>,
+
+    /// Report the diagnostics of a lint as errors, failing if any are found, overriding the
+    /// project manifest (repeatable)
+    #[arg(short = 'D', long, value_name = "LINT", global = true)]
+    pub deny: Vec<DiagnosticCode>,
 
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -23,78 +45,384 @@ pub enum Commands {
     Debug,
 
     /// Play the parsed dialogue interactively
-    Play,
+    Play {
+        /// Path to save progress to at each choice, resuming from it if it already exists
+        #[arg(short, long)]
+        save: Option<String>,
+    },
 
     /// Convert the parsed dialogue to a specific format
     Convert {
-        /// Output format (json, yaml, etc)
+        /// Output format (json, yaml, ron, toml or pickle, depending on enabled features)
         #[arg(short, long)]
         format: String,
 
         /// Output file path (default: stdout)
         path: Option<String>,
+
+        /// Leave source locations out of the output
+        #[arg(long)]
+        no_locations: bool,
+
+        /// Convert the compiled dialogue, with every reference resolved, rather than the parsed
+        /// dialogue
+        #[arg(long)]
+        compiled: bool,
     },
+
+    /// Convert the parsed dialogue to each output listed in the project manifest
+    Build,
+
+    /// Analyze the dialogue for problems with how it plays, such as navigating to sections which
+    /// don't exist, failing if any errors are found
+    Check,
+
+    /// List every lint, along with the level it's reported at
+    Lints,
 }
 
 pub fn execute() {
     let cli = Cli::parse();
 
-    let raw_dialogue = std::fs::read_to_string(&cli.file).expect("Failed to read file");
+    let mut manifest = load_manifest(cli.manifest.as_deref());
+    let lints = lint_registry(&cli, manifest.as_ref());
+
+    // Loading the manifest's sources applies its lints, so include those given on the command line
+    if let Some(manifest) = &mut manifest {
+        manifest.lints = lints.clone();
+    }
+
+    if let Some(Commands::Lints) = &cli.command {
+        list_lints(&lints);
+        return;
+    }
 
     println!("\nParsing dialogue...");
 
     let start = std::time::Instant::now();
 
-    let parse_result = parse(raw_dialogue);
-    let dialogue = parse_result.dialogue;
+    let project = load_dialogue(cli.file.as_deref(), manifest.as_ref(), &lints);
+
+    if project.has_errors() {
+        for diagnostic in &project.diagnostics {
+            eprint!("\n{}", project.render_diagnostic(diagnostic));
+        }
+
+        let parse_errors = project.into_result().unwrap_err();
+        eprintln!("\nParsing failed: {parse_errors}");
+        std::process::exit(1);
+    }
 
     let duration: std::time::Duration = start.elapsed();
     println!("Parsing succeeded in: {duration:?}");
 
-    if !parse_result.warnings.is_empty() {
-        println!("\nWarnings:");
-        for warning in &parse_result.warnings {
-            println!("  {warning}");
-        }
+    for diagnostic in &project.diagnostics {
+        print!("\n{}", project.render_diagnostic(diagnostic));
     }
 
     println!();
 
+    if let Some(Commands::Check) = &cli.command {
+        check(&project, manifest.as_ref(), &lints);
+        return;
+    }
+
+    let mut dialogue = project.dialogue;
+
     match &cli.command {
         Some(Commands::Debug) => {
             println!("{dialogue:#?}");
         }
 
-        Some(Commands::Convert { format, path }) => {
-            let output = match format.as_str() {
-                "json" => Some(serde_json::to_string_pretty(&dialogue).unwrap()),
-                "yaml" => Some(serde_yaml::to_string(&dialogue).unwrap()),
-                "ron" => Some(ron::to_string(&dialogue).unwrap()),
-                "toml" => Some(toml::to_string_pretty(&dialogue).unwrap()),
-                "pickle" => Some({
-                    let bytes = serde_pickle::to_vec(&dialogue, SerOptions::default()).unwrap();
-                    base64::prelude::BASE64_STANDARD.encode(bytes)
-                }),
-                _ => None,
-            };
-
-            let Some(output) = output else {
-                eprintln!("Unsupported format: {format}");
+        Some(Commands::Convert {
+            format,
+            path,
+            no_locations,
+            compiled,
+        }) => {
+            if *compiled {
+                write_output(&compile_dialogue(&dialogue), format, path.as_deref());
                 return;
+            }
+
+            if *no_locations {
+                dialogue.strip_source_locations();
+            }
+
+            write_output(&dialogue, format, path.as_deref());
+        }
+
+        Some(Commands::Build) => {
+            let Some(manifest) = &manifest else {
+                eprintln!("Building requires a project manifest ({MANIFEST_FILE_NAME})");
+                std::process::exit(1);
             };
 
-            let Some(path) = path else {
-                println!("{output}");
+            if manifest.outputs.is_empty() {
+                eprintln!("No outputs listed in the project manifest");
                 return;
-            };
+            }
+
+            let mut stripped_dialogue = dialogue.clone();
+            stripped_dialogue.strip_source_locations();
 
-            std::fs::write(path, output).expect("Failed to write output file");
+            // Only compile if an output needs it, so other outputs build despite compile errors
+            let compiled_dialogue = manifest
+                .outputs
+                .iter()
+                .any(|output| output.compiled)
+                .then(|| compile_dialogue(&dialogue));
 
-            println!("Output written to: {path}");
+            for output in &manifest.outputs {
+                let path = output
+                    .path
+                    .as_ref()
+                    .map(|path| manifest.root.join(path).to_string_lossy().into_owned());
+
+                if output.compiled
+                    && let Some(compiled_dialogue) = &compiled_dialogue
+                {
+                    write_output(compiled_dialogue, &output.format, path.as_deref());
+                    continue;
+                }
+
+                let dialogue = if output.locations {
+                    &dialogue
+                } else {
+                    &stripped_dialogue
+                };
+
+                write_output(dialogue, &output.format, path.as_deref());
+            }
         }
 
-        Some(Commands::Play) | None => {
-            play(dialogue);
+        Some(Commands::Check) => unreachable!("Checks are handled before the dialogue is moved"),
+
+        Some(Commands::Lints) => unreachable!("Lints are listed before the dialogue is parsed"),
+
+        Some(Commands::Play { save }) => {
+            play_dialogue(&dialogue, manifest.as_ref(), save.as_deref());
+        }
+
+        None => {
+            play_dialogue(&dialogue, manifest.as_ref(), None);
+        }
+    }
+}
+
+/// Loads the project manifest from the given path, or the nearest one if none is given.
+fn load_manifest(path: Option<&str>) -> Option<Manifest> {
+    let path = match path {
+        Some(path) => path.into(),
+        None => {
+            let current_dir = std::env::current_dir().expect("Failed to get current directory");
+            let path = Manifest::find(&current_dir)?;
+
+            // Keep paths short when the manifest is within the current directory
+            path.strip_prefix(&current_dir)
+                .map(|path| path.to_path_buf())
+                .unwrap_or(path)
+        }
+    };
+
+    match Manifest::load(&path) {
+        Ok(manifest) => Some(manifest),
+        Err(error) => {
+            eprintln!("{error} ({})", path.display());
+            std::process::exit(1);
         }
     }
 }
+
+/// Gets the level of each lint from the manifest, overridden by those given on the command line.
+fn lint_registry(cli: &Cli, manifest: Option<&Manifest>) -> LintRegistry {
+    let mut lints = manifest
+        .map(|manifest| manifest.lints.clone())
+        .unwrap_or_default();
+
+    let levels = [
+        (&cli.allow, LintLevel::Allow),
+        (&cli.warn, LintLevel::Warn),
+        (&cli.deny, LintLevel::Deny),
+    ];
+
+    for (codes, level) in levels {
+        for &code in codes {
+            lints.set(code, level);
+        }
+    }
+
+    lints
+}
+
+/// Prints every lint along with the level it's reported at.
+fn list_lints(lints: &LintRegistry) {
+    let width = DiagnosticCode::ALL
+        .iter()
+        .map(|code| code.as_str().len())
+        .max()
+        .unwrap_or_default();
+
+    for code in DiagnosticCode::ALL {
+        println!("{:width$}  {}", code.as_str(), lints.level(code));
+    }
+}
+
+/// Loads the given dialogue file with the given lint levels, or the sources of the manifest if no
+/// file is given.
+fn load_dialogue(
+    file: Option<&str>,
+    manifest: Option<&Manifest>,
+    lints: &LintRegistry,
+) -> ProjectResult {
+    match (file, manifest) {
+        (Some(file), _) => {
+            let mut project = load_project(file);
+            lints.apply(&mut project.diagnostics);
+            project
+        }
+
+        (None, Some(manifest)) => match manifest.load_project() {
+            Ok(project) => project,
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        },
+
+        (None, None) => {
+            eprintln!(
+                "No dialogue file given, and no {MANIFEST_FILE_NAME} found in the current directory or its parents"
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Compiles the dialogue, exiting with its errors if any reference can't be resolved.
+fn compile_dialogue(dialogue: &Dialogue) -> CompiledDialogue {
+    match compile(dialogue) {
+        Ok(compiled_dialogue) => compiled_dialogue,
+        Err(compile_errors) => {
+            for error in &compile_errors.errors {
+                eprintln!("Error: {error}");
+            }
+
+            eprintln!("\nCompilation failed: {compile_errors}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Analyzes the dialogue, starting from the manifest's entry section, and exits with a failure if
+/// any errors are found, including from denied lints.
+fn check(project: &ProjectResult, manifest: Option<&Manifest>, lints: &LintRegistry) {
+    let entry = manifest.and_then(|manifest| manifest.project.entry.as_deref());
+    let mut diagnostics = analyze(&project.dialogue, &project.sources, entry);
+
+    lints.apply(&mut diagnostics);
+
+    for diagnostic in &diagnostics {
+        print!("\n{}", project.render_diagnostic(diagnostic));
+    }
+
+    // Diagnostics from parsing have already been printed, but count toward the totals
+    let count = |severity| {
+        project
+            .diagnostics
+            .iter()
+            .chain(&diagnostics)
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let errors = count(Severity::Error);
+    let warnings = count(Severity::Warning);
+
+    println!("\nCheck found {errors} errors and {warnings} warnings");
+
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Plays the dialogue from the manifest's entry section, or from the progress saved at the given
+/// path if there is any, saving progress there at each choice.
+fn play_dialogue(dialogue: &Dialogue, manifest: Option<&Manifest>, save_path: Option<&str>) {
+    let program = compile_dialogue(dialogue);
+    let entry = manifest.and_then(|manifest| manifest.project.entry.as_deref());
+
+    let saved_state = save_path.and_then(|path| std::fs::read_to_string(path).ok());
+
+    let runner = match saved_state {
+        Some(saved_state) => match serde_json::from_str::<RunnerState>(&saved_state) {
+            Ok(state) => match DialogueRunner::restore(program.clone(), state) {
+                Ok(runner) => {
+                    println!("Resuming saved progress...\n");
+                    runner
+                }
+                Err(error) => {
+                    eprintln!("{error}, so starting over\n");
+                    start_runner(program, entry)
+                }
+            },
+            Err(error) => {
+                eprintln!("Failed to parse saved progress: {error}, so starting over\n");
+                start_runner(program, entry)
+            }
+        },
+        None => start_runner(program, entry),
+    };
+
+    play(runner, |runner| {
+        if let Some(path) = save_path {
+            save_progress(runner, path);
+        }
+    });
+}
+
+/// Saves the runner's progress to the given path, reporting any failure without stopping play.
+fn save_progress(runner: &DialogueRunner, path: &str) {
+    let state = match serde_json::to_string(&runner.save_state()) {
+        Ok(state) => state,
+        Err(error) => {
+            eprintln!("Failed to serialize progress: {error}");
+            return;
+        }
+    };
+
+    if let Err(error) = std::fs::write(path, state) {
+        eprintln!("Failed to write saved progress to {path}: {error}");
+    }
+}
+
+/// Creates a runner starting from the given entry section, or the first section if none is given.
+fn start_runner(program: CompiledDialogue, entry: Option<&str>) -> DialogueRunner {
+    match entry {
+        Some(entry) => DialogueRunner::with_entry(program, entry),
+        None => DialogueRunner::new(program),
+    }
+}
+
+/// Converts the dialogue to the given format, writing it to the given path or stdout.
+fn write_output(dialogue: &impl Serialize, format: &str, path: Option<&str>) {
+    let Some(output) = convert(dialogue, format) else {
+        eprintln!(
+            "Unsupported format: {format} (supported: {})",
+            FORMATS.join(", ")
+        );
+        return;
+    };
+
+    let Some(path) = path else {
+        println!("{output}");
+        return;
+    };
+
+    if let Some(directory) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(directory).expect("Failed to create output directory");
+    }
+
+    std::fs::write(path, output).expect("Failed to write output file");
+
+    println!("Output written to: {path}");
+}
@@ -0,0 +1,486 @@
+//! Compiler from parsed dialogue into a resolved form for playback.
+
+#[cfg(test)]
+mod tests;
+mod types;
+
+pub use types::*;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Dialogue, DialogueExpression, DialogueLine, DialogueStep, DialogueText, DialogueValue,
+    SourceLocation, TextSegment,
+};
+
+/// A reference which could not be resolved while compiling.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompileError {
+    pub message: String,
+    /// Where the step containing the reference was defined, if known
+    pub location: Option<SourceLocation>,
+}
+
+/// Errors which prevented a dialogue from compiling
+#[derive(Debug)]
+pub struct CompileErrors {
+    pub errors: Vec<CompileError>,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{} ({location})", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl fmt::Display for CompileErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.errors.first() {
+            Some(first) => write!(f, "{first} ({} errors in total)", self.errors.len()),
+            None => write!(f, "Dialogue failed to compile"),
+        }
+    }
+}
+
+impl std::error::Error for CompileErrors {}
+
+/// Compiles a dialogue for playback, resolving every reference to a section, variable, function
+/// or actor.
+///
+/// Every unresolved reference is reported, rather than stopping at the first.
+///
+/// # Example
+/// ```
+/// use dialogue_syntax::{compile, parse};
+///
+/// let dialogue = parse("# intro\nHello!\n=> outro\n# outro\nBye!".to_string()).dialogue;
+/// let compiled = compile(&dialogue).unwrap();
+///
+/// assert_eq!(compiled.sections.len(), 2);
+/// ```
+pub fn compile(dialogue: &Dialogue) -> Result<CompiledDialogue, CompileErrors> {
+    let mut compiler = Compiler::new(dialogue);
+
+    let sections = dialogue
+        .sections
+        .iter()
+        .map(|section| CompiledSection {
+            name: section.name.clone(),
+            steps: compiler.compile_steps(&section.steps),
+        })
+        .collect();
+
+    if !compiler.errors.is_empty() {
+        return Err(CompileErrors {
+            errors: compiler.errors,
+        });
+    }
+
+    let mut compiled = CompiledDialogue {
+        hash: 0,
+        variables: compiler.variables,
+        functions: compiler.functions,
+        sections,
+    };
+    compiled.hash = hash_dialogue(&compiled);
+
+    Ok(compiled)
+}
+
+/// Hashes the content of a compiled dialogue, ignoring its existing hash.
+///
+/// The dialogue is hashed in its TOML form, which orders maps by key, using FNV-1a so the hash
+/// stays the same across builds and platforms.
+fn hash_dialogue(compiled: &CompiledDialogue) -> u32 {
+    const OFFSET_BASIS: u32 = 0x811c9dc5;
+    const PRIME: u32 = 0x01000193;
+
+    let content = CompiledDialogue {
+        hash: 0,
+        ..compiled.clone()
+    };
+    let serialized = toml::Value::try_from(&content)
+        .and_then(|value| toml::to_string(&value))
+        .expect("Compiled dialogue should be representable as TOML");
+
+    serialized.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// State of a compilation in progress.
+struct Compiler<'a> {
+    dialogue: &'a Dialogue,
+    /// Index of each section by name, where the first of any duplicates wins
+    section_indices: HashMap<&'a str, usize>,
+    variable_slots: HashMap<String, usize>,
+    variables: Vec<CompiledVariable>,
+    function_indices: HashMap<String, usize>,
+    functions: Vec<CompiledFunction>,
+    /// Location of the step being compiled, for reporting errors
+    location: Option<SourceLocation>,
+    errors: Vec<CompileError>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(dialogue: &'a Dialogue) -> Self {
+        let mut section_indices = HashMap::new();
+
+        for (index, section) in dialogue.sections.iter().enumerate() {
+            section_indices
+                .entry(section.name.as_str())
+                .or_insert(index);
+        }
+
+        // Sort definitions by name, so the same dialogue always compiles to the same slots
+        let mut variables = dialogue
+            .variables
+            .iter()
+            .map(|(name, value)| CompiledVariable {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect::<Vec<_>>();
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut functions = dialogue
+            .functions
+            .iter()
+            .map(|(name, function)| CompiledFunction {
+                name: name.clone(),
+                args: function.args.clone().unwrap_or_default(),
+                result: function.result.clone(),
+            })
+            .collect::<Vec<_>>();
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let variable_slots = variables
+            .iter()
+            .enumerate()
+            .map(|(slot, variable)| (variable.name.clone(), slot))
+            .collect();
+
+        let function_indices = functions
+            .iter()
+            .enumerate()
+            .map(|(index, function)| (function.name.clone(), index))
+            .collect();
+
+        Self {
+            dialogue,
+            section_indices,
+            variable_slots,
+            variables,
+            function_indices,
+            functions,
+            location: None,
+            errors: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>) {
+        self.errors.push(CompileError {
+            message: message.into(),
+            location: self.location.clone(),
+        });
+    }
+
+    fn compile_steps(&mut self, steps: &[DialogueStep]) -> Vec<CompiledStep> {
+        // Nested steps leave errors in the rest of their enclosing step at its location
+        let enclosing_location = self.location.clone();
+
+        let compiled_steps = steps
+            .iter()
+            .filter_map(|step| self.compile_step(step))
+            .collect();
+
+        self.location = enclosing_location;
+        compiled_steps
+    }
+
+    /// Compiles a step, or gives `None` for steps without any effect on playback.
+    fn compile_step(&mut self, step: &DialogueStep) -> Option<CompiledStep> {
+        self.location = step.location().cloned();
+
+        let compiled_step = match step {
+            DialogueStep::Comment { .. } => return None,
+
+            DialogueStep::LogInfo { text, .. } => CompiledStep::LogInfo(text.clone()),
+            DialogueStep::LogWarning { text, .. } => CompiledStep::LogWarning(text.clone()),
+            DialogueStep::LogError { text, .. } => CompiledStep::LogError(text.clone()),
+
+            DialogueStep::VariableAssign { name, value, .. } => CompiledStep::VariableAssign {
+                variable: self.resolve_variable(name),
+                value: self.compile_expression(value),
+            },
+
+            DialogueStep::Page { lines, .. } => {
+                let mut compiled_lines = Vec::new();
+                let mut responses = Vec::new();
+
+                for line in lines {
+                    match line {
+                        DialogueLine::Text {
+                            text, annotations, ..
+                        } => compiled_lines.push(CompiledLine {
+                            speaker: None,
+                            text: self.compile_text(text),
+                            annotations: annotations.clone(),
+                        }),
+
+                        DialogueLine::SpeakerText {
+                            speaker,
+                            text,
+                            annotations,
+                            ..
+                        } => {
+                            // Speakers don't need defining, so fall back to their id for a name
+                            let name = self
+                                .dialogue
+                                .actors
+                                .get(speaker)
+                                .map_or(speaker, |actor| &actor.name);
+
+                            compiled_lines.push(CompiledLine {
+                                speaker: Some(CompiledSpeaker {
+                                    id: speaker.clone(),
+                                    name: name.clone(),
+                                }),
+                                text: self.compile_text(text),
+                                annotations: annotations.clone(),
+                            });
+                        }
+
+                        DialogueLine::Response { text, pages, .. } => {
+                            let text = self.compile_text(text);
+                            let steps = self.compile_steps(pages);
+
+                            responses.push(CompiledResponse { text, steps });
+                        }
+                    }
+                }
+
+                CompiledStep::Page {
+                    lines: compiled_lines,
+                    responses,
+                }
+            }
+
+            DialogueStep::SectionBounce { target, .. } => {
+                CompiledStep::SectionBounce(self.resolve_section(target))
+            }
+
+            DialogueStep::SectionJump { target, .. } => {
+                CompiledStep::SectionJump(self.resolve_section(target))
+            }
+
+            DialogueStep::EndJump { .. } => CompiledStep::EndJump,
+            DialogueStep::TerminateJump { .. } => CompiledStep::TerminateJump,
+
+            DialogueStep::Conditional {
+                condition,
+                then_steps,
+                else_steps,
+                ..
+            } => CompiledStep::Conditional {
+                condition: self.compile_expression(condition),
+                then_steps: self.compile_steps(then_steps),
+                else_steps: self.compile_steps(else_steps),
+            },
+
+            DialogueStep::Repeat { count, steps, .. } => CompiledStep::Repeat {
+                count: *count,
+                steps: self.compile_steps(steps),
+            },
+
+            DialogueStep::While {
+                condition, steps, ..
+            } => CompiledStep::While {
+                condition: self.compile_expression(condition),
+                steps: self.compile_steps(steps),
+            },
+
+            DialogueStep::Each {
+                array, item, steps, ..
+            } => {
+                let array = self.resolve_variable(array);
+
+                // Loop items only exist within their loop, so are given a slot of their own,
+                // shadowing any variable of the same name until the loop ends
+                let shadowed = self.variable_slots.get(item).copied();
+                let item_slot = self.add_variable(item, DialogueValue::Text(String::new()));
+                let steps = self.compile_steps(steps);
+
+                match shadowed {
+                    Some(slot) => self.variable_slots.insert(item.clone(), slot),
+                    None => self.variable_slots.remove(item),
+                };
+
+                CompiledStep::Each {
+                    array,
+                    item: item_slot,
+                    steps,
+                }
+            }
+
+            DialogueStep::FunctionCall {
+                name, args, result, ..
+            } => self.compile_function_call(name, args, result.as_deref()),
+        };
+
+        Some(compiled_step)
+    }
+
+    /// Compiles a function call, filling in the defaults of any arguments left out.
+    fn compile_function_call(
+        &mut self,
+        name: &str,
+        args: &HashMap<String, DialogueValue>,
+        result: Option<&str>,
+    ) -> CompiledStep {
+        let result = result.map(|result| self.resolve_variable(result));
+
+        let Some(&function_index) = self.function_indices.get(name) else {
+            self.error(format!("Function not found: {name}"));
+
+            return CompiledStep::FunctionCall {
+                function: 0,
+                args: args.clone(),
+                result,
+            };
+        };
+
+        let function = &self.functions[function_index];
+        let mut call_args = function.args.clone();
+        let has_result = function.result.is_some();
+
+        for (arg_name, arg_value) in args {
+            if !call_args.contains_key(arg_name) {
+                self.error(format!("Function argument not found: {name}({arg_name})"));
+            }
+
+            call_args.insert(arg_name.clone(), arg_value.clone());
+        }
+
+        if result.is_some() && !has_result {
+            self.error(format!("Function has no result: {name}"));
+        }
+
+        CompiledStep::FunctionCall {
+            function: function_index,
+            args: call_args,
+            result,
+        }
+    }
+
+    fn compile_expression(&mut self, expression: &DialogueExpression) -> CompiledExpression {
+        match expression {
+            DialogueExpression::Value(value) => CompiledExpression::Value(value.clone()),
+
+            DialogueExpression::Variable(name) => {
+                CompiledExpression::Variable(self.resolve_variable(name))
+            }
+
+            // Actor properties never change, so are resolved to their values
+            DialogueExpression::ActorProperty { actor, property } => {
+                CompiledExpression::Value(self.resolve_actor_property(actor, property))
+            }
+
+            DialogueExpression::Unary { operator, operand } => CompiledExpression::Unary {
+                operator: *operator,
+                operand: Box::new(self.compile_expression(operand)),
+            },
+
+            DialogueExpression::Binary {
+                left,
+                operator,
+                right,
+            } => CompiledExpression::Binary {
+                left: Box::new(self.compile_expression(left)),
+                operator: *operator,
+                right: Box::new(self.compile_expression(right)),
+            },
+        }
+    }
+
+    fn compile_text(&mut self, text: &DialogueText) -> CompiledText {
+        let spans = text
+            .spans
+            .iter()
+            .map(|span| {
+                let content = match &span.content {
+                    TextSegment::Literal(literal) => CompiledSegment::Literal(literal.clone()),
+                    TextSegment::Variable(name) => {
+                        CompiledSegment::Variable(self.resolve_variable(name))
+                    }
+                    TextSegment::ActorProperty { actor, property } => CompiledSegment::Literal(
+                        self.resolve_actor_property(actor, property).to_string(),
+                    ),
+                };
+
+                CompiledSpan {
+                    content,
+                    style: span.style.clone(),
+                }
+            })
+            .collect();
+
+        CompiledText { spans }
+    }
+
+    fn resolve_section(&mut self, name: &str) -> usize {
+        match self.section_indices.get(name) {
+            Some(&index) => index,
+            None => {
+                self.error(format!("Section not found: {name}"));
+                0
+            }
+        }
+    }
+
+    fn resolve_variable(&mut self, name: &str) -> usize {
+        match self.variable_slots.get(name) {
+            Some(&slot) => slot,
+            None => {
+                self.error(format!("Variable not found: {name}"));
+                0
+            }
+        }
+    }
+
+    /// Gets the value of an actor's property, falling back to the actor's display name for the
+    /// `name` property.
+    fn resolve_actor_property(&mut self, actor: &str, property: &str) -> DialogueValue {
+        let Some(found_actor) = self.dialogue.actors.get(actor) else {
+            self.error(format!("Actor not found: {actor}"));
+            return DialogueValue::Text(String::new());
+        };
+
+        match found_actor.properties.get(property) {
+            Some(value) => value.clone(),
+            None if property == "name" => DialogueValue::Text(found_actor.name.clone()),
+            None => {
+                self.error(format!("Actor property not found: {actor}.{property}"));
+                DialogueValue::Text(String::new())
+            }
+        }
+    }
+
+    fn add_variable(&mut self, name: &str, value: DialogueValue) -> usize {
+        let slot = self.variables.len();
+
+        self.variables.push(CompiledVariable {
+            name: name.to_string(),
+            value,
+        });
+        self.variable_slots.insert(name.to_string(), slot);
+
+        slot
+    }
+}
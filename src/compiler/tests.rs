@@ -0,0 +1,159 @@
+#[cfg(test)]
+use crate::*;
+
+/// Helper function for tests - compiles the given dialogue, ignoring any diagnostics
+fn compile_test_helper(input: &str) -> Result<CompiledDialogue, CompileErrors> {
+    compile(&parse(input.to_string()).dialogue)
+}
+
+#[test]
+fn test_compile_resolves_references() {
+    let input = r"
+@oscar
+name: Oscar Robin
+
+$gold: 5
+$armed: false
+!give(item=sword): false
+
+# intro
+oscar: Hello {@oscar.name}, you have {$gold} gold
+$armed = !give()
+// Comments are left out
+=> shop
+
+# shop
+[if=$gold > @oscar.name]
+  => intro
+~";
+
+    let compiled = compile_test_helper(input).unwrap();
+
+    // Slots are given in order of name
+    assert_eq!(compiled.variable_slot("armed"), Some(0));
+    assert_eq!(compiled.variable_slot("gold"), Some(1));
+    assert_eq!(compiled.section_index("shop"), Some(1));
+
+    assert_eq!(
+        compiled.sections[0].steps,
+        vec![
+            CompiledStep::Page {
+                lines: vec![CompiledLine {
+                    speaker: Some(CompiledSpeaker {
+                        id: "oscar".to_string(),
+                        name: "Oscar Robin".to_string(),
+                    }),
+                    text: CompiledText {
+                        spans: vec![
+                            CompiledSpan {
+                                content: CompiledSegment::Literal("Hello ".to_string()),
+                                style: TextStyle::default(),
+                            },
+                            CompiledSpan {
+                                content: CompiledSegment::Literal("Oscar Robin".to_string()),
+                                style: TextStyle::default(),
+                            },
+                            CompiledSpan {
+                                content: CompiledSegment::Literal(", you have ".to_string()),
+                                style: TextStyle::default(),
+                            },
+                            CompiledSpan {
+                                content: CompiledSegment::Variable(1),
+                                style: TextStyle::default(),
+                            },
+                            CompiledSpan {
+                                content: CompiledSegment::Literal(" gold".to_string()),
+                                style: TextStyle::default(),
+                            },
+                        ],
+                    },
+                    annotations: Default::default(),
+                }],
+                responses: Vec::new(),
+            },
+            CompiledStep::FunctionCall {
+                function: 0,
+                args: std::collections::HashMap::from([(
+                    "item".to_string(),
+                    DialogueValue::Text("sword".to_string())
+                )]),
+                result: Some(0),
+            },
+            CompiledStep::SectionJump(1),
+        ]
+    );
+
+    assert_eq!(
+        compiled.sections[1].steps,
+        vec![CompiledStep::Conditional {
+            condition: CompiledExpression::Binary {
+                left: Box::new(CompiledExpression::Variable(1)),
+                operator: BinaryOperator::Greater,
+                right: Box::new(CompiledExpression::Value(DialogueValue::Text(
+                    "Oscar Robin".to_string()
+                ))),
+            },
+            then_steps: vec![CompiledStep::SectionJump(0)],
+            else_steps: Vec::new(),
+        }]
+    );
+}
+
+#[test]
+fn test_compile_unresolved_references() {
+    let input = r"
+$gold: 5
+!give(item=sword)
+
+# intro
+=> missing
+[if=$silver > 1]
+  $gold = !give(amount=1)
+~
+Hello {@oscar.name}";
+
+    let errors = compile_test_helper(input).unwrap_err().errors;
+
+    let messages = errors
+        .iter()
+        .map(|error| error.to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        messages,
+        vec![
+            "Section not found: missing (line 6, column 1)",
+            "Variable not found: silver (line 7, column 1)",
+            "Function argument not found: give(amount) (line 8, column 3)",
+            "Function has no result: give (line 8, column 3)",
+            "Actor not found: oscar (line 10, column 1)",
+        ]
+    );
+}
+
+#[test]
+fn test_compile_serialization() {
+    let input = r"
+$names: [Ann, Bob]
+
+# intro
+~ EACH $names as $name
+  Hi {$name}
+  - Bye
+    => END
+~";
+
+    let compiled = compile_test_helper(input).unwrap();
+
+    // Loop items are given a slot of their own
+    assert_eq!(compiled.variable_slot("name"), Some(1));
+
+    let json = serde_json::to_string(&compiled).unwrap();
+    let deserialized: CompiledDialogue = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(deserialized, compiled);
+    assert!(convert(&compiled, "toml").is_some());
+
+    // Hashes don't depend on the order maps happen to be in
+    assert_eq!(compile_test_helper(input).unwrap().hash, compiled.hash);
+}
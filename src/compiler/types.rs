@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BinaryOperator, DialogueValue, TextStyle, UnaryOperator};
+
+/// Dialogue compiled for playback, with every reference resolved ahead of time.
+///
+/// Sections, variables and functions are referred to by their index, so nothing is looked up by
+/// name while dialogue plays. Comments, source locations and actor definitions are left out, with
+/// references to actors resolved to their values.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompiledDialogue {
+    /// Fingerprint of the compiled content, which changes whenever the dialogue does
+    pub hash: u32,
+    /// Variables by slot, along with their initial values
+    pub variables: Vec<CompiledVariable>,
+    pub functions: Vec<CompiledFunction>,
+    pub sections: Vec<CompiledSection>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompiledVariable {
+    pub name: String,
+    pub value: DialogueValue,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompiledFunction {
+    pub name: String,
+    pub args: HashMap<String, DialogueValue>,
+    pub result: Option<DialogueValue>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompiledSection {
+    pub name: String,
+    pub steps: Vec<CompiledStep>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompiledStep {
+    LogInfo(String),
+    LogWarning(String),
+    LogError(String),
+    VariableAssign {
+        variable: usize,
+        value: CompiledExpression,
+    },
+    Page {
+        lines: Vec<CompiledLine>,
+        /// Responses offered as a choice after the page's lines
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        responses: Vec<CompiledResponse>,
+    },
+    SectionBounce(usize),
+    SectionJump(usize),
+    EndJump,
+    TerminateJump,
+    Conditional {
+        condition: CompiledExpression,
+        then_steps: Vec<CompiledStep>,
+        else_steps: Vec<CompiledStep>,
+    },
+    Repeat {
+        count: usize,
+        steps: Vec<CompiledStep>,
+    },
+    While {
+        condition: CompiledExpression,
+        steps: Vec<CompiledStep>,
+    },
+    Each {
+        array: usize,
+        item: usize,
+        steps: Vec<CompiledStep>,
+    },
+    FunctionCall {
+        function: usize,
+        /// Arguments of the call, including the defaults of any left out
+        args: HashMap<String, DialogueValue>,
+        /// Variable the result is stored in
+        result: Option<usize>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompiledLine {
+    pub speaker: Option<CompiledSpeaker>,
+    pub text: CompiledText,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub annotations: HashMap<String, DialogueValue>,
+}
+
+/// The actor speaking a line, along with their display name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompiledSpeaker {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompiledResponse {
+    pub text: CompiledText,
+    pub steps: Vec<CompiledStep>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompiledText {
+    pub spans: Vec<CompiledSpan>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompiledSpan {
+    pub content: CompiledSegment,
+    #[serde(default, skip_serializing_if = "TextStyle::is_plain")]
+    pub style: TextStyle,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompiledSegment {
+    Literal(String),
+    Variable(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompiledExpression {
+    Value(DialogueValue),
+    Variable(usize),
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<CompiledExpression>,
+    },
+    Binary {
+        left: Box<CompiledExpression>,
+        operator: BinaryOperator,
+        right: Box<CompiledExpression>,
+    },
+}
+
+impl CompiledDialogue {
+    /// Gets the index of the named section.
+    pub fn section_index(&self, name: &str) -> Option<usize> {
+        self.sections
+            .iter()
+            .position(|section| section.name == name)
+    }
+
+    /// Gets the slot of the named variable.
+    pub fn variable_slot(&self, name: &str) -> Option<usize> {
+        self.variables
+            .iter()
+            .position(|variable| variable.name == name)
+    }
+}
@@ -0,0 +1,56 @@
+//! Converter from parsed or compiled dialogue into alternate data formats.
+//!
+//! Each format is behind a cargo feature of the same name, apart from TOML which is always
+//! available.
+
+use serde::Serialize;
+
+/// Every format dialogue can be converted to, given the enabled features.
+pub const FORMATS: &[&str] = &[
+    #[cfg(feature = "json")]
+    "json",
+    #[cfg(feature = "yaml")]
+    "yaml",
+    #[cfg(feature = "ron")]
+    "ron",
+    "toml",
+    #[cfg(feature = "pickle")]
+    "pickle",
+];
+
+/// Converts the dialogue into the given format, or `None` if the format is unsupported.
+///
+/// Either a parsed [`Dialogue`](crate::Dialogue) or a
+/// [`CompiledDialogue`](crate::CompiledDialogue) can be converted.
+///
+/// Binary formats such as pickle are encoded as base64 text.
+///
+/// # Example
+/// ```
+/// use dialogue_syntax::{convert, parse};
+///
+/// let result = parse("# intro\nHello world!".to_string());
+///
+/// let output = convert(&result.dialogue, "toml");
+/// assert!(output.is_some());
+/// ```
+pub fn convert<T: Serialize>(dialogue: &T, format: &str) -> Option<String> {
+    match format {
+        #[cfg(feature = "json")]
+        "json" => Some(serde_json::to_string_pretty(dialogue).unwrap()),
+        #[cfg(feature = "yaml")]
+        "yaml" => Some(serde_yaml::to_string(dialogue).unwrap()),
+        #[cfg(feature = "ron")]
+        "ron" => Some(ron::to_string(dialogue).unwrap()),
+        "toml" => Some(toml::to_string_pretty(dialogue).unwrap()),
+        #[cfg(feature = "pickle")]
+        "pickle" => Some({
+            use base64::Engine;
+
+            let bytes =
+                serde_pickle::to_vec(dialogue, serde_pickle::SerOptions::default()).unwrap();
+            base64::prelude::BASE64_STANDARD.encode(bytes)
+        }),
+        _ => None,
+    }
+}
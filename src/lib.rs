@@ -0,0 +1,16 @@
+//! Parser, analyzer, compiler, converter and player for the Lex dialogue syntax.
+
+pub mod analysis;
+pub use analysis::*;
+
+pub mod compiler;
+pub use compiler::*;
+
+pub mod converter;
+pub use converter::*;
+
+pub mod parser;
+pub use parser::*;
+
+pub mod player;
+pub use player::*;
@@ -1,3 +1,5 @@
 pub const FILE_EXTENSION: &str = "lex";
 
+pub const MANIFEST_FILE_NAME: &str = "lex.toml";
+
 pub const META_SECTION_NAME: &str = "meta";
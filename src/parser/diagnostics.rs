@@ -0,0 +1,442 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A problem found in dialogue source, pointing at where it occurred.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    /// Path of the file the problem is in, if parsed from a file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub span: SourceSpan,
+    /// Other places in the source which help explain the problem
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<RelatedSpan>,
+    /// Suggested ways to resolve the problem
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Stable identifier for each kind of diagnostic, so tools can match on them regardless of their
+/// message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticCode {
+    EmptySectionName,
+    MissingValue,
+    UnclosedArguments,
+    UnexpectedBlockMarker,
+    UnclosedBlock,
+    DuplicateElse,
+    LoopElse,
+    InvalidRepeatCount,
+    ExpectedVariable,
+    NotAnArray,
+    InvalidExpression,
+    UndefinedVariable,
+    UndefinedActor,
+    UndefinedActorProperty,
+    MissingActorProperty,
+    UndefinedFunction,
+    UndefinedArgument,
+    ArgumentTypeMismatch,
+    MissingResult,
+    DanglingAnnotations,
+    ResponseAnnotations,
+    UnclosedReference,
+    UnclosedMarkup,
+    UnmatchedClosingTag,
+    UnresolvedInclude,
+    IncludeCycle,
+    UnreadableFile,
+    UndefinedSection,
+    UnreachableSection,
+    EndlessSection,
+    BounceCycle,
+    UnreachableStep,
+    TypeMismatch,
+    IncompatibleTypes,
+    ArrayMisuse,
+    DuplicateDefinition,
+    DuplicateSection,
+    SilentActor,
+    UnusedVariable,
+    UnusedFunction,
+    UnknownLint,
+    MisplacedDefinition,
+}
+
+/// A range of the source, as byte offsets along with the line and column where it starts.
+///
+/// Lines and columns are one-based, with columns counted in characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A secondary span of a diagnostic, with a note on how it relates.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelatedSpan {
+    /// Path of the file the span is in, if not the same file as the diagnostic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub span: SourceSpan,
+    pub message: String,
+}
+
+impl DiagnosticCode {
+    /// Every kind of diagnostic, in the order they're declared.
+    pub const ALL: [DiagnosticCode; 42] = [
+        DiagnosticCode::EmptySectionName,
+        DiagnosticCode::MissingValue,
+        DiagnosticCode::UnclosedArguments,
+        DiagnosticCode::UnexpectedBlockMarker,
+        DiagnosticCode::UnclosedBlock,
+        DiagnosticCode::DuplicateElse,
+        DiagnosticCode::LoopElse,
+        DiagnosticCode::InvalidRepeatCount,
+        DiagnosticCode::ExpectedVariable,
+        DiagnosticCode::NotAnArray,
+        DiagnosticCode::InvalidExpression,
+        DiagnosticCode::UndefinedVariable,
+        DiagnosticCode::UndefinedActor,
+        DiagnosticCode::UndefinedActorProperty,
+        DiagnosticCode::MissingActorProperty,
+        DiagnosticCode::UndefinedFunction,
+        DiagnosticCode::UndefinedArgument,
+        DiagnosticCode::ArgumentTypeMismatch,
+        DiagnosticCode::MissingResult,
+        DiagnosticCode::DanglingAnnotations,
+        DiagnosticCode::ResponseAnnotations,
+        DiagnosticCode::UnclosedReference,
+        DiagnosticCode::UnclosedMarkup,
+        DiagnosticCode::UnmatchedClosingTag,
+        DiagnosticCode::UnresolvedInclude,
+        DiagnosticCode::IncludeCycle,
+        DiagnosticCode::UnreadableFile,
+        DiagnosticCode::UndefinedSection,
+        DiagnosticCode::UnreachableSection,
+        DiagnosticCode::EndlessSection,
+        DiagnosticCode::BounceCycle,
+        DiagnosticCode::UnreachableStep,
+        DiagnosticCode::TypeMismatch,
+        DiagnosticCode::IncompatibleTypes,
+        DiagnosticCode::ArrayMisuse,
+        DiagnosticCode::DuplicateDefinition,
+        DiagnosticCode::DuplicateSection,
+        DiagnosticCode::SilentActor,
+        DiagnosticCode::UnusedVariable,
+        DiagnosticCode::UnusedFunction,
+        DiagnosticCode::UnknownLint,
+        DiagnosticCode::MisplacedDefinition,
+    ];
+
+    /// The code as written in output and configuration, such as `undefined-variable`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticCode::EmptySectionName => "empty-section-name",
+            DiagnosticCode::MissingValue => "missing-value",
+            DiagnosticCode::UnclosedArguments => "unclosed-arguments",
+            DiagnosticCode::UnexpectedBlockMarker => "unexpected-block-marker",
+            DiagnosticCode::UnclosedBlock => "unclosed-block",
+            DiagnosticCode::DuplicateElse => "duplicate-else",
+            DiagnosticCode::LoopElse => "loop-else",
+            DiagnosticCode::InvalidRepeatCount => "invalid-repeat-count",
+            DiagnosticCode::ExpectedVariable => "expected-variable",
+            DiagnosticCode::NotAnArray => "not-an-array",
+            DiagnosticCode::InvalidExpression => "invalid-expression",
+            DiagnosticCode::UndefinedVariable => "undefined-variable",
+            DiagnosticCode::UndefinedActor => "undefined-actor",
+            DiagnosticCode::UndefinedActorProperty => "undefined-actor-property",
+            DiagnosticCode::MissingActorProperty => "missing-actor-property",
+            DiagnosticCode::UndefinedFunction => "undefined-function",
+            DiagnosticCode::UndefinedArgument => "undefined-argument",
+            DiagnosticCode::ArgumentTypeMismatch => "argument-type-mismatch",
+            DiagnosticCode::MissingResult => "missing-result",
+            DiagnosticCode::DanglingAnnotations => "dangling-annotations",
+            DiagnosticCode::ResponseAnnotations => "response-annotations",
+            DiagnosticCode::UnclosedReference => "unclosed-reference",
+            DiagnosticCode::UnclosedMarkup => "unclosed-markup",
+            DiagnosticCode::UnmatchedClosingTag => "unmatched-closing-tag",
+            DiagnosticCode::UnresolvedInclude => "unresolved-include",
+            DiagnosticCode::IncludeCycle => "include-cycle",
+            DiagnosticCode::UnreadableFile => "unreadable-file",
+            DiagnosticCode::UndefinedSection => "undefined-section",
+            DiagnosticCode::UnreachableSection => "unreachable-section",
+            DiagnosticCode::EndlessSection => "endless-section",
+            DiagnosticCode::BounceCycle => "bounce-cycle",
+            DiagnosticCode::UnreachableStep => "unreachable-step",
+            DiagnosticCode::TypeMismatch => "type-mismatch",
+            DiagnosticCode::IncompatibleTypes => "incompatible-types",
+            DiagnosticCode::ArrayMisuse => "array-misuse",
+            DiagnosticCode::DuplicateDefinition => "duplicate-definition",
+            DiagnosticCode::DuplicateSection => "duplicate-section",
+            DiagnosticCode::SilentActor => "silent-actor",
+            DiagnosticCode::UnusedVariable => "unused-variable",
+            DiagnosticCode::UnusedFunction => "unused-function",
+            DiagnosticCode::UnknownLint => "unknown-lint",
+            DiagnosticCode::MisplacedDefinition => "misplaced-definition",
+        }
+    }
+
+    /// The severity diagnostics with this code are reported at.
+    ///
+    /// Malformed syntax and dialogue which can't play as written are errors, whereas dialogue which
+    /// may not behave as intended is a warning.
+    pub fn default_severity(self) -> Severity {
+        match self {
+            DiagnosticCode::EmptySectionName
+            | DiagnosticCode::MissingValue
+            | DiagnosticCode::UnclosedArguments
+            | DiagnosticCode::UnexpectedBlockMarker
+            | DiagnosticCode::UnclosedBlock
+            | DiagnosticCode::DuplicateElse
+            | DiagnosticCode::LoopElse
+            | DiagnosticCode::InvalidRepeatCount
+            | DiagnosticCode::ExpectedVariable
+            | DiagnosticCode::InvalidExpression
+            | DiagnosticCode::MissingActorProperty
+            | DiagnosticCode::UnclosedReference
+            | DiagnosticCode::IncludeCycle
+            | DiagnosticCode::UnreadableFile
+            | DiagnosticCode::UndefinedSection
+            | DiagnosticCode::BounceCycle
+            | DiagnosticCode::MisplacedDefinition => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic at its code's default severity.
+    pub fn new(code: DiagnosticCode, message: impl Into<String>, span: SourceSpan) -> Self {
+        Self {
+            severity: code.default_severity(),
+            code,
+            message: message.into(),
+            file: None,
+            span,
+            related: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// Adds a related span with a note on how it relates.
+    pub fn with_related(&mut self, span: SourceSpan, message: impl Into<String>) -> &mut Self {
+        self.related.push(RelatedSpan {
+            file: None,
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Adds a related span in the given file, which may differ from the diagnostic's file, with a
+    /// note on how it relates.
+    pub fn with_related_in(
+        &mut self,
+        file: Option<String>,
+        span: SourceSpan,
+        message: impl Into<String>,
+    ) -> &mut Self {
+        let file = file.filter(|file| self.file.as_ref() != Some(file));
+
+        self.related.push(RelatedSpan {
+            file,
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Adds a suggested way to resolve the problem.
+    pub fn with_suggestion(&mut self, suggestion: impl Into<String>) -> &mut Self {
+        self.suggestions.push(suggestion.into());
+        self
+    }
+
+    /// Renders the diagnostic for display in a terminal, with snippets of the source it points
+    /// at.
+    ///
+    /// # Example
+    /// ```text
+    /// warning[undefined-variable]: Static variable definition not found [gold]
+    ///  --> intro.lex:4:2
+    ///   |
+    /// 4 | $gold = 10
+    ///   |  ^^^^
+    ///   = help: Define the variable first: `$gold: value`
+    /// ```
+    pub fn render(&self, source: &str, path: &str) -> String {
+        let gutter_width = std::iter::once(&self.span)
+            .chain(self.related.iter().map(|related| &related.span))
+            .map(|span| span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(gutter_width);
+
+        let mut output = format!("{}[{}]: {}\n", self.severity, self.code, self.message);
+
+        // Problems with a file as a whole, such as failing to read it, have no snippet to show
+        if self.span.line == 0 {
+            output += &format!("{gutter}--> {path}\n");
+        } else {
+            output += &format!(
+                "{gutter}--> {path}:{}:{}\n",
+                self.span.line, self.span.column
+            );
+            output += &render_snippet(source, &self.span, '^', "", gutter_width);
+        }
+
+        for related in &self.related {
+            // Only the diagnostic's own source is at hand, so spans elsewhere are just located
+            match &related.file {
+                Some(file) => {
+                    output += &format!(
+                        "{gutter} = note: {} ({file}:{}:{})\n",
+                        related.message, related.span.line, related.span.column
+                    );
+                }
+                None => {
+                    output +=
+                        &render_snippet(source, &related.span, '-', &related.message, gutter_width);
+                }
+            }
+        }
+
+        for suggestion in &self.suggestions {
+            output += &format!("{gutter} = help: {suggestion}\n");
+        }
+
+        output
+    }
+}
+
+/// Renders the source line of a span, underlined with the given marker and followed by a label.
+fn render_snippet(
+    source: &str,
+    span: &SourceSpan,
+    marker: char,
+    label: &str,
+    gutter_width: usize,
+) -> String {
+    let gutter = " ".repeat(gutter_width);
+    let line_text = source
+        .lines()
+        .nth(span.line.saturating_sub(1))
+        .unwrap_or_default();
+
+    // Underline within the line, even if the span continues beyond it
+    let span_text = source.get(span.start..span.end).unwrap_or_default();
+    let span_text = span_text.lines().next().unwrap_or_default();
+    let underline_width = span_text.chars().count().max(1);
+
+    let underline = format!(
+        "{}{}",
+        " ".repeat(span.column.saturating_sub(1)),
+        marker.to_string().repeat(underline_width)
+    );
+
+    let underline = if label.is_empty() {
+        underline
+    } else {
+        format!("{underline} {label}")
+    };
+
+    format!(
+        "{gutter} |\n{:>gutter_width$} | {line_text}\n{gutter} | {underline}\n",
+        span.line
+    )
+}
+
+/// Locates spans within a source, given slices of it.
+#[derive(Debug)]
+pub struct SourceMap<'a> {
+    pub source: &'a str,
+    /// Byte offset at which each line starts
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Gets the span of a slice of the source, or `None` if the slice is from elsewhere.
+    pub fn span_of(&self, slice: &str) -> Option<SourceSpan> {
+        let start = (slice.as_ptr() as usize).checked_sub(self.source.as_ptr() as usize)?;
+        let end = start + slice.len();
+
+        if end > self.source.len() {
+            return None;
+        }
+
+        Some(self.span_between(start, end))
+    }
+
+    /// Gets the span of a line's content, excluding surrounding whitespace.
+    pub fn line_span(&self, line: usize) -> SourceSpan {
+        let line_start = self
+            .line_starts
+            .get(line.saturating_sub(1))
+            .copied()
+            .unwrap_or(self.source.len());
+
+        let line_text = self.source[line_start..].lines().next().unwrap_or_default();
+        let content = line_text.trim();
+        let start = line_start + (line_text.len() - line_text.trim_start().len());
+
+        self.span_between(start, start + content.len())
+    }
+
+    fn span_between(&self, start: usize, end: usize) -> SourceSpan {
+        let line_index = self
+            .line_starts
+            .partition_point(|&line_start| line_start <= start)
+            .saturating_sub(1);
+        let line_start = self.line_starts[line_index];
+
+        SourceSpan {
+            start,
+            end,
+            line: line_index + 1,
+            column: self.source[line_start..start].chars().count() + 1,
+        }
+    }
+}
@@ -1,22 +1,147 @@
 use super::*;
 use std::collections::*;
-use std::iter::Peekable;
+use std::fmt;
+use std::iter::{Enumerate, Peekable};
 use std::str::Lines;
 use std::string::*;
 
-/// Result of parsing a dialogue, including any warnings encountered
+/// Source lines being parsed, paired with their zero-based line index
+type SourceLines<'a> = Peekable<Enumerate<Lines<'a>>>;
+
+/// Result of parsing a dialogue, including any diagnostics encountered
 #[derive(Debug)]
 pub struct ParseResult {
     pub dialogue: Dialogue,
-    pub warnings: Vec<String>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseResult {
+    /// Whether any error diagnostics were encountered, meaning the source is malformed.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// Converts the result into an error if any error diagnostics were encountered.
+    pub fn into_result(self) -> Result<ParseResult, ParseErrors> {
+        if self.has_errors() {
+            return Err(ParseErrors {
+                diagnostics: self.diagnostics,
+            });
+        }
+
+        Ok(self)
+    }
+}
+
+/// Errors which prevented a dialogue from parsing cleanly, along with every other diagnostic
+/// encountered while recovering from them
+#[derive(Debug)]
+pub struct ParseErrors {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseErrors {
+    /// Gets the error diagnostics, excluding any warnings.
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut errors = self.errors();
+
+        match errors.next() {
+            Some(first) => {
+                match &first.file {
+                    Some(file) if first.span.line == 0 => write!(f, "{file}")?,
+                    Some(file) => write!(f, "{file}, line {}", first.span.line)?,
+                    None => write!(f, "Line {}", first.span.line)?,
+                }
+
+                write!(
+                    f,
+                    ": {} ({} errors in total)",
+                    first.message,
+                    errors.count() + 1
+                )
+            }
+            None => write!(f, "Dialogue failed to parse"),
+        }
+    }
 }
 
+impl std::error::Error for ParseErrors {}
+
 /// Context passed to parsing functions for consistent error reporting and data access
 #[derive(Debug)]
 pub struct ParseContext<'a> {
     pub dialogue: &'a Dialogue,
+    /// The source being parsed, for locating diagnostics
+    pub source: &'a SourceMap<'a>,
+    /// Path of the file being parsed, if parsing from a file
+    pub file: Option<&'a str>,
     pub current_line: usize,
-    pub warnings: &'a mut Vec<String>,
+    pub diagnostics: &'a mut Vec<Diagnostic>,
+    /// Variables which only exist within the block being parsed, such as loop items
+    pub scoped_variables: Vec<String>,
+}
+
+impl ParseContext<'_> {
+    /// Whether the given variable is defined, either statically or by an enclosing block.
+    pub fn has_variable(&self, name: &str) -> bool {
+        self.dialogue.variables.contains_key(name)
+            || self.scoped_variables.iter().any(|scoped| scoped == name)
+    }
+
+    /// Gets the span of a slice of the source, falling back to the current line if the slice is
+    /// from elsewhere.
+    pub fn span_of(&self, at: &str) -> SourceSpan {
+        self.source
+            .span_of(at)
+            .unwrap_or_else(|| self.source.line_span(self.current_line))
+    }
+
+    /// Gets the source location of a slice of the source, for retaining in the dialogue.
+    pub fn location_of(&self, at: &str) -> SourceLocation {
+        let span = self.span_of(at);
+
+        SourceLocation {
+            file: self.file.map(str::to_string),
+            line: span.line,
+            column: span.column,
+        }
+    }
+
+    /// Reports a diagnostic spanning the current line.
+    pub fn report(&mut self, code: DiagnosticCode, message: impl Into<String>) -> &mut Diagnostic {
+        let span = self.source.line_span(self.current_line);
+
+        self.push_diagnostic(Diagnostic::new(code, message, span))
+    }
+
+    /// Reports a diagnostic spanning the given slice of the source.
+    pub fn report_at(
+        &mut self,
+        code: DiagnosticCode,
+        message: impl Into<String>,
+        at: &str,
+    ) -> &mut Diagnostic {
+        let span = self.span_of(at);
+
+        self.push_diagnostic(Diagnostic::new(code, message, span))
+    }
+
+    fn push_diagnostic(&mut self, mut diagnostic: Diagnostic) -> &mut Diagnostic {
+        diagnostic.file = self.file.map(str::to_string);
+
+        self.diagnostics.push(diagnostic);
+        self.diagnostics.last_mut().unwrap()
+    }
 }
 
 /// Parses the given dialogue string into a dialogue data structure.
@@ -28,11 +153,11 @@ pub struct ParseContext<'a> {
 /// * `from` - The dialogue content as a string
 ///
 /// # Returns
-/// A `ParseResult` containing the parsed dialogue and any warnings encountered
+/// A `ParseResult` containing the parsed dialogue and any diagnostics encountered
 ///
 /// # Example
 /// ```
-/// use dialogue_syntax::parser::functions::parse;
+/// use dialogue_syntax::parse;
 ///
 /// let input = r#"
 /// # intro
@@ -48,20 +173,49 @@ pub struct ParseContext<'a> {
 /// assert!(!result.dialogue.sections.is_empty());
 /// ```
 pub fn parse(from: String) -> ParseResult {
+    parse_source(from, None, &mut report_unresolved_include)
+}
+
+/// Parses the given dialogue string as per [`parse`], recording the path of the file it was read
+/// from in source locations and diagnostics.
+pub fn parse_file(from: String, file: &str) -> ParseResult {
+    parse_source(from, Some(file), &mut report_unresolved_include)
+}
+
+/// Handles an include directive, given the path as written and the context it was found in.
+///
+/// Returns the dialogue parsed from the included file, whose definitions become available to the
+/// rest of the including file, or `None` if it could not be included.
+pub type IncludeHandler<'a> = dyn FnMut(&str, &mut ParseContext) -> Option<Dialogue> + 'a;
+
+/// Parses the given dialogue string as per [`parse_file`], resolving include directives with the
+/// given handler.
+///
+/// Only the definitions of included files are merged into the result, leaving their sections to
+/// the handler. See [`ProjectLoader`] for loading a set of files from disk.
+pub fn parse_with_includes(from: String, file: &str, include: &mut IncludeHandler) -> ParseResult {
+    parse_source(from, Some(file), include)
+}
+
+fn parse_source(from: String, file: Option<&str>, include: &mut IncludeHandler) -> ParseResult {
     // Setup
     let mut dialogue = Dialogue::default();
-    let mut warnings = Vec::new();
+    let mut diagnostics = Vec::new();
+    let source = SourceMap::new(&from);
 
     let mut current_section = DialogueSection {
         name: META_SECTION_NAME.to_string(),
         ..Default::default()
     };
 
-    let mut lines = from.lines().peekable();
-    let mut line_number = 0;
+    let mut lines = from.lines().enumerate().peekable();
 
-    while let Some(line) = lines.next().map(|line| line.trim()) {
-        line_number += 1;
+    // Functions can only be defined before the first section, as within sections their
+    // definitions would be mistaken for calls
+    let mut in_section = false;
+
+    while let Some((line_index, raw_line)) = lines.next() {
+        let line = raw_line.trim();
 
         // Skip for empty lines
         if line.is_empty() {
@@ -70,11 +224,28 @@ pub fn parse(from: String) -> ParseResult {
 
         let mut context = ParseContext {
             dialogue: &dialogue,
-            current_line: line_number,
-            warnings: &mut warnings,
+            source: &source,
+            file,
+            current_line: line_index + 1,
+            diagnostics: &mut diagnostics,
+            scoped_variables: Vec::new(),
         };
 
-        if let Some(new_section) = parse_section(line) {
+        if let Some(mut new_section) = parse_section(line) {
+            new_section.location = Some(context.location_of(line));
+
+            if new_section.name.is_empty() {
+                context
+                    .report(
+                        DiagnosticCode::EmptySectionName,
+                        "Section is missing a name",
+                    )
+                    .with_suggestion(format!(
+                        "Name the section: `{} section_name`",
+                        syntax::prefixes::SECTION
+                    ));
+            }
+
             // If the current section has pages, push it to the dialogue
             if !current_section.steps.is_empty() {
                 dialogue.sections.push(current_section);
@@ -82,61 +253,209 @@ pub fn parse(from: String) -> ParseResult {
 
             // Start a new section
             current_section = new_section;
+            in_section = true;
 
             continue;
         }
 
-        if let Some((actor_id, actor)) = parse_actor_definition(line, &mut lines) {
-            dialogue.actors.insert(actor_id, actor);
-            continue;
-        }
+        if let Some(include_path) = parse_include(line) {
+            if include_path.is_empty() {
+                context
+                    .report(DiagnosticCode::MissingValue, "Include is missing a path")
+                    .with_suggestion(format!(
+                        "Give the path of the file to include: `{} path/to/file.{FILE_EXTENSION}`",
+                        syntax::directives::INCLUDE
+                    ));
+                continue;
+            }
+
+            if let Some(included) = include(include_path, &mut context) {
+                // Included definitions which replace those from other files are reported as the
+                // project loads
+                for redefinition in find_redefinitions(context.dialogue, &included) {
+                    if redefinition.previous.file.as_deref() == file {
+                        report_duplicate_definition(&redefinition, &mut context);
+                    }
+                }
+
+                dialogue.actors.extend(included.actors);
+                dialogue.variables.extend(included.variables);
+                dialogue.functions.extend(included.functions);
+                dialogue
+                    .variable_locations
+                    .extend(included.variable_locations);
+            }
 
-        if let Some(log_step) = parse_log_step(line) {
-            current_section.steps.push(log_step);
             continue;
         }
 
-        if let Some(comment_step) = parse_comment_step(line) {
-            current_section.steps.push(comment_step);
+        if let Some((actor_id, mut actor)) = parse_actor_definition(line, &mut lines) {
+            let previous = context.dialogue.actors.get(&actor_id);
+            let location = context.location_of(line);
+            check_duplicate_definition(
+                "Actor",
+                &actor_id,
+                previous.and_then(|actor| actor.location.as_ref()),
+                &location,
+                &mut context,
+            );
+
+            actor.location = Some(location);
+            dialogue.actors.insert(actor_id, actor);
             continue;
         }
 
-        if let Some((function_id, function)) = parse_function_definition(line) {
+        if !in_section
+            && let Some((function_id, mut function)) = parse_function_definition(line, &mut context)
+        {
+            let previous = context.dialogue.functions.get(&function_id);
+            let location = context.location_of(line);
+            check_duplicate_definition(
+                "Function",
+                &function_id,
+                previous.and_then(|function| function.location.as_ref()),
+                &location,
+                &mut context,
+            );
+
+            function.location = Some(location);
             dialogue.functions.insert(function_id, function);
             continue;
         }
 
-        if let Some((variable_name, variable_value)) = parse_variable_definition(line) {
+        if let Some((variable_name, variable_value)) = parse_variable_definition(line, &mut context)
+        {
+            let previous = context.dialogue.variable_locations.get(&variable_name);
+            let location = context.location_of(line);
+            check_duplicate_definition(
+                "Variable",
+                &variable_name,
+                previous,
+                &location,
+                &mut context,
+            );
+
+            dialogue
+                .variable_locations
+                .insert(variable_name.clone(), location);
             dialogue.variables.insert(variable_name, variable_value);
             continue;
         }
 
-        if let Some(variable_assign_step) = parse_variable_assignment(line, &mut context) {
-            current_section.steps.push(variable_assign_step);
+        if let Some(step) = parse_step(raw_line, &mut lines, &mut context) {
+            current_section.steps.push(step);
             continue;
         }
+    }
 
-        if let Some(section_bounce_step) = parse_section_bounce(line) {
-            current_section.steps.push(section_bounce_step);
-            continue;
-        }
+    if !current_section.steps.is_empty() {
+        dialogue.sections.push(current_section);
+    }
 
-        if let Some(section_jump_step) = parse_section_jump(line) {
-            current_section.steps.push(section_jump_step);
-            continue;
-        }
+    LintSuppressions::find(&from).apply(&mut diagnostics);
 
-        if let Some(new_page) = parse_page(line, &mut lines, &mut context) {
-            current_section.steps.push(new_page);
-            continue;
-        }
+    ParseResult {
+        dialogue,
+        diagnostics,
     }
+}
 
-    if !current_section.steps.is_empty() {
-        dialogue.sections.push(current_section);
+/// Parses the given dialogue string, failing if it is malformed.
+///
+/// Parsing recovers from errors in order to report as many as possible, so on failure every
+/// diagnostic encountered is returned, including warnings. On success, the result may still
+/// contain warnings.
+///
+/// # Example
+/// ```
+/// use dialogue_syntax::try_parse;
+///
+/// let result = try_parse("$gold:".to_string());
+/// assert!(result.is_err());
+/// ```
+pub fn try_parse(from: String) -> Result<ParseResult, ParseErrors> {
+    parse(from).into_result()
+}
+
+/// Parses a single dialogue step, consuming any further lines which belong to it.
+///
+/// Steps are the elements which make up a section's content, as opposed to definitions
+/// (actors, variables and functions) and section headers. The line is given untrimmed, as
+/// indentation determines which lines belong to nested responses.
+fn parse_step(
+    raw_line: &str,
+    lines: &mut SourceLines,
+    context: &mut ParseContext,
+) -> Option<DialogueStep> {
+    let location = context.location_of(raw_line.trim());
+
+    let mut step = parse_step_content(raw_line, lines, context)?;
+    step.set_location(Some(location));
+
+    Some(step)
+}
+
+/// Parses the content of a single dialogue step, without its source location.
+fn parse_step_content(
+    raw_line: &str,
+    lines: &mut SourceLines,
+    context: &mut ParseContext,
+) -> Option<DialogueStep> {
+    let line = raw_line.trim();
+
+    if let Some(log_step) = parse_log_step(line) {
+        return Some(log_step);
+    }
+
+    if let Some(comment_step) = parse_comment_step(line) {
+        check_lint_suppression(line, context);
+        return Some(comment_step);
+    }
+
+    if let Some(variable_assign_step) = parse_variable_assignment(line, context) {
+        return Some(variable_assign_step);
+    }
+
+    if let Some(function_call_step) = parse_function_call_step(line, context) {
+        return Some(function_call_step);
+    }
+
+    if let Some(function_definition) = line.strip_prefix(syntax::prefixes::FUNCTION) {
+        report_misplaced_function_definition(function_definition, context);
+        return None;
+    }
+
+    if let Some(section_bounce_step) = parse_section_bounce(line) {
+        return Some(section_bounce_step);
+    }
+
+    if let Some(section_jump_step) = parse_section_jump(line) {
+        return Some(section_jump_step);
+    }
+
+    if let Some(conditional_step) = parse_conditional(line, lines, context) {
+        return Some(conditional_step);
+    }
+
+    if let Some(loop_step) = parse_loop(line, lines, context) {
+        return Some(loop_step);
     }
 
-    ParseResult { dialogue, warnings }
+    // Closing and else markers are consumed by the block they belong to, so any reaching here
+    // are stray, as are any unknown keywords
+    if let Some(marker) = parse_block_marker(line) {
+        context.report(
+            DiagnosticCode::UnexpectedBlockMarker,
+            format!(
+                "Unexpected block marker ({})",
+                marker.unwrap_or(syntax::blocks::MARKER)
+            ),
+        );
+
+        return None;
+    }
+
+    parse_page(line, get_indentation(raw_line), lines, context)
 }
 
 // =====================================
@@ -151,7 +470,7 @@ pub fn parse(from: String) -> ParseResult {
 /// `# section_name`
 ///
 /// # Example
-/// ```
+/// ```text
 /// # intro
 /// # main_menu
 /// # ending
@@ -163,10 +482,41 @@ fn parse_section(line: &str) -> Option<DialogueSection> {
 
     Some(DialogueSection {
         name: section_name.to_string(),
-        steps: Vec::new(),
+        ..Default::default()
     })
 }
 
+/// Parses an include directive, giving the path of the file to include.
+///
+/// Includes pull the actors, variables, functions and sections of another file into the
+/// dialogue. Paths are relative to the including file, and the file extension may be left out.
+///
+/// # Syntax
+/// `%include path/to/file`
+///
+/// # Example
+/// ```text
+/// %include actors
+/// %include "chapters/chapter 1.lex"
+/// ```
+fn parse_include(line: &str) -> Option<&str> {
+    let include_path = line.strip_prefix(syntax::directives::INCLUDE)?;
+
+    // Require whitespace after the keyword, so `%includes` is left as text
+    if !include_path.is_empty() && !include_path.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let include_path = include_path.trim();
+
+    Some(
+        include_path
+            .strip_prefix(syntax::delimiters::QUOTE)
+            .and_then(|path| path.strip_suffix(syntax::delimiters::QUOTE))
+            .unwrap_or(include_path),
+    )
+}
+
 /// Parses log steps for debugging and development.
 ///
 /// Log steps help with dialogue debugging by providing different severity levels.
@@ -178,7 +528,7 @@ fn parse_section(line: &str) -> Option<DialogueSection> {
 /// - `//! text` - Error log (serious problems)
 ///
 /// # Example
-/// ```
+/// ```text
 /// /// Starting dialogue system
 /// //? Player name not set, using default
 /// //! Critical error: save file corrupted
@@ -186,17 +536,26 @@ fn parse_section(line: &str) -> Option<DialogueSection> {
 fn parse_log_step(line: &str) -> Option<DialogueStep> {
     // Check for info log - `/// text`
     if let Some(log_text) = line.strip_prefix(syntax::comments::INFO) {
-        return Some(DialogueStep::LogInfo(log_text.trim().to_string()));
+        return Some(DialogueStep::LogInfo {
+            text: log_text.trim().to_string(),
+            location: None,
+        });
     }
 
     // Check for warning log - `//? text`
     if let Some(log_text) = line.strip_prefix(syntax::comments::WARNING) {
-        return Some(DialogueStep::LogWarning(log_text.trim().to_string()));
+        return Some(DialogueStep::LogWarning {
+            text: log_text.trim().to_string(),
+            location: None,
+        });
     }
 
     // Check for error log - `//! text`
     line.strip_prefix(syntax::comments::ERROR)
-        .map(|log_text| DialogueStep::LogError(log_text.trim().to_string()))
+        .map(|log_text| DialogueStep::LogError {
+            text: log_text.trim().to_string(),
+            location: None,
+        })
 }
 
 /// Parses comment steps for documentation and notes.
@@ -208,13 +567,41 @@ fn parse_log_step(line: &str) -> Option<DialogueStep> {
 /// `// text`
 ///
 /// # Example
-/// ```
+/// ```text
 /// // This section handles the introduction
 /// // Remember to update voice acting scripts
 /// ```
 fn parse_comment_step(line: &str) -> Option<DialogueStep> {
     let comment_text = line.strip_prefix(syntax::comments::BASIC)?;
-    Some(DialogueStep::Comment(comment_text.trim().to_string()))
+    Some(DialogueStep::Comment {
+        text: comment_text.trim().to_string(),
+        location: None,
+    })
+}
+
+/// Reports any lints listed by a suppression comment which don't exist.
+///
+/// # Syntax
+/// `// lex-allow: lint-name, other-lint-name`
+///
+/// # Notes
+/// - Lints are allowed on the next line which isn't blank or another comment, see
+///   [`LintSuppressions`]
+/// - Generates a warning for each lint which doesn't exist
+fn check_lint_suppression(line: &str, context: &mut ParseContext) {
+    let Some(lints) = parse_lint_suppression(line) else {
+        return;
+    };
+
+    for lint in lints {
+        if lint.parse::<DiagnosticCode>().is_err() {
+            context.report_at(
+                DiagnosticCode::UnknownLint,
+                format!("Lint not found [{lint}]"),
+                lint,
+            );
+        }
+    }
 }
 
 // =====================================
@@ -236,21 +623,41 @@ fn parse_comment_step(line: &str) -> Option<DialogueStep> {
 /// - Arrays: `$items: [sword, potion, key]`
 ///
 /// # Example
-/// ```
+/// ```text
 /// $player_name: Hero
 /// $level: 1
 /// $has_key: false
 /// $inventory: [sword, potion]
 /// ```
-fn parse_variable_definition(line: &str) -> Option<(String, DialogueValue)> {
+///
+/// # Notes
+/// - Generates an error if the value is missing, in which case the variable is defined as empty
+///   text
+fn parse_variable_definition(
+    line: &str,
+    context: &mut ParseContext,
+) -> Option<(String, DialogueValue)> {
     let (variable_name, variable_value) = line
         .strip_prefix(syntax::prefixes::VARIABLE)
         .and_then(|line| line.split_once(syntax::delimiters::SEPARATOR))?;
 
     let variable_name = variable_name.trim().to_lowercase();
-    let variable_value = parse_value(variable_value.trim());
+    let variable_value = variable_value.trim();
+
+    if variable_value.is_empty() {
+        context
+            .report(
+                DiagnosticCode::MissingValue,
+                format!("Variable definition is missing a value [{variable_name}]"),
+            )
+            .with_suggestion(format!(
+                "Give the variable an initial value: `{}{variable_name}{} value`",
+                syntax::prefixes::VARIABLE,
+                syntax::delimiters::SEPARATOR
+            ));
+    }
 
-    Some((variable_name, variable_value))
+    Some((variable_name, parse_value(variable_value)))
 }
 
 /// Parses variable assignments for runtime state changes.
@@ -259,36 +666,44 @@ fn parse_variable_definition(line: &str) -> Option<(String, DialogueValue)> {
 /// dialogue execution. The variable must be defined before it can be assigned.
 ///
 /// # Syntax
-/// `$variable_name = value`
+/// `$variable_name = expression`
 ///
 /// # Example
-/// ```
+/// ```text
 /// $health = 90
 /// $current_location = forest
 /// $has_talked_to_npc = true
+/// $gold = $gold + 10
 /// ```
 ///
 /// # Notes
 /// - Generates a warning if the variable hasn't been defined
 /// - Variable names are converted to lowercase for consistency
+/// - Assigned values are expressions, see [`parse_expression`]
+/// - Assigning a function call stores its result, see [`parse_function_call_step`]
 fn parse_variable_assignment(line: &str, context: &mut ParseContext) -> Option<DialogueStep> {
     let (variable_name, variable_value) = line
         .strip_prefix(syntax::prefixes::VARIABLE)
         .and_then(|line| line.split_once(syntax::delimiters::ASSIGNMENT))?;
 
-    let variable_name = variable_name.trim();
-    let variable_value = parse_value(variable_value.trim());
+    let raw_variable_name = variable_name.trim();
+    let variable_name = raw_variable_name.to_lowercase();
+    let variable_value = variable_value.trim();
 
-    if !context.dialogue.variables.contains_key(variable_name) {
-        context.warnings.push(format!(
-            "Line {}: Static variable definition not found [{}]",
-            context.current_line, variable_name
-        ));
+    if !context.has_variable(&variable_name) {
+        report_undefined_variable(&variable_name, raw_variable_name, context);
+    }
+
+    // Check for function call results
+    // `$variable_name = !function_name()`
+    if let Some(call_text) = variable_value.strip_prefix(syntax::prefixes::FUNCTION) {
+        return Some(parse_function_call(call_text, Some(variable_name), context));
     }
 
     Some(DialogueStep::VariableAssign {
-        name: variable_name.to_lowercase(),
-        value: variable_value,
+        name: variable_name,
+        value: parse_expression(variable_value, context),
+        location: None,
     })
 }
 
@@ -306,7 +721,7 @@ fn parse_variable_assignment(line: &str, context: &mut ParseContext) -> Option<D
 /// `=><= section_name`
 ///
 /// # Example
-/// ```
+/// ```text
 /// Do you want to visit the shop?
 /// =><= shop_menu
 /// Welcome back! Anything else?
@@ -318,7 +733,10 @@ fn parse_variable_assignment(line: &str, context: &mut ParseContext) -> Option<D
 /// 3. Execution returns to the line after the bounce
 fn parse_section_bounce(line: &str) -> Option<DialogueStep> {
     let jump_section = line.strip_prefix(syntax::navigation::BOUNCE)?;
-    Some(DialogueStep::SectionBounce(jump_section.trim().to_string()))
+    Some(DialogueStep::SectionBounce {
+        target: jump_section.trim().to_string(),
+        location: None,
+    })
 }
 
 /// Parses section jump steps for permanent navigation.
@@ -335,7 +753,7 @@ fn parse_section_bounce(line: &str) -> Option<DialogueStep> {
 /// - `=> section_name` - Jumps to the named section
 ///
 /// # Example
-/// ```
+/// ```text
 /// Thanks for playing!
 /// => credits
 ///
@@ -348,114 +766,1023 @@ fn parse_section_jump(line: &str) -> Option<DialogueStep> {
     let jump_section = jump_section.trim();
 
     let new_step = match jump_section.to_lowercase().as_str() {
-        "end" => DialogueStep::EndJump,
-        "terminate" => DialogueStep::TerminateJump,
-        _ => DialogueStep::SectionJump(jump_section.to_string()),
+        "end" => DialogueStep::EndJump { location: None },
+        "terminate" => DialogueStep::TerminateJump { location: None },
+        _ => DialogueStep::SectionJump {
+            target: jump_section.to_string(),
+            location: None,
+        },
     };
 
     Some(new_step)
 }
 
 // =====================================
-// Utility Functions
+// Block Parsing Functions
 // =====================================
 
-/// Converts a string value into the appropriate DialogueValue type.
+/// Describes how a block of steps was ended.
+#[derive(Debug, PartialEq)]
+enum BlockEnd {
+    /// Closed by a lone block marker: `~`
+    Close,
+    /// Closed by an alternate branch marker: `~ ELSE`
+    Else,
+    /// Ran into a new section or the end of the dialogue without being closed
+    Unterminated,
+}
+
+/// Parses a conditional block, which plays one of two branches depending on a condition.
 ///
-/// This function attempts to parse the input string as different data types
-/// in order of specificity: boolean, number, array, then defaults to text.
+/// # Syntax
+/// ```text
+/// [if=condition]
+/// steps played when the condition is truthy
+/// ~ ELSE
+/// steps played otherwise
+/// ~
+/// ```
 ///
-/// # Type Detection Order
-/// 1. **Boolean**: `true` or `false` (case-sensitive)
-/// 2. **Number**: Any valid f64 (e.g., `42`, `3.14`, `-1.5`)
-/// 3. **Array**: Comma-separated values in brackets `[item1, item2, item3]`
-/// 4. **Text**: Everything else (default)
+/// The `~ ELSE` branch is optional.
 ///
-/// # Examples
-/// ```
-/// parse_value("true")        // -> DialogueValue::Boolean(true)
-/// parse_value("42")          // -> DialogueValue::Number(42.0)
-/// parse_value("3.14")        // -> DialogueValue::Number(3.14)
-/// parse_value("[a, b, c]")   // -> DialogueValue::Array(vec!["a", "b", "c"])
-/// parse_value("hello")       // -> DialogueValue::Text("hello")
-/// parse_value("not_a_bool")  // -> DialogueValue::Text("not_a_bool")
+/// # Example
+/// ```text
+/// [if=$has_key]
+/// The door swings open.
+/// ~ ELSE
+/// The door is locked.
+/// ~
 /// ```
-fn parse_value(from: &str) -> DialogueValue {
-    // Check for boolean value
-    if let Ok(bool_value) = str::parse::<bool>(from) {
-        return DialogueValue::Boolean(bool_value);
-    }
+fn parse_conditional(
+    line: &str,
+    lines: &mut SourceLines,
+    context: &mut ParseContext,
+) -> Option<DialogueStep> {
+    let condition = parse_conditional_header(line).map(|raw| parse_expression(raw, context))?;
 
-    // Check for numeric value
-    if let Ok(number_value) = str::parse::<f64>(from) {
-        return DialogueValue::Number(number_value);
-    }
+    let (then_steps, mut block_end) = parse_block_steps(lines, context);
 
-    // Check for array of unquoted strings
-    // `[first item, second item, third item]`
-    if let Some(comma_separated_values) = from
-        .strip_prefix(syntax::delimiters::ARRAY_START)
-        .and_then(|s| s.strip_suffix(syntax::delimiters::ARRAY_END))
-    {
-        let values: Vec<String> = comma_separated_values
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+    let mut else_steps = Vec::new();
 
-        return DialogueValue::Array(values);
+    if block_end == BlockEnd::Else {
+        (else_steps, block_end) = parse_block_steps(lines, context);
+
+        if block_end == BlockEnd::Else {
+            let block_span = context.span_of(line);
+
+            context
+                .report(
+                    DiagnosticCode::DuplicateElse,
+                    "Conditional block has more than one else branch",
+                )
+                .with_related(block_span, "conditional block starts here");
+        }
     }
 
-    // Default to textual value
-    DialogueValue::Text(from.to_string())
+    if block_end == BlockEnd::Unterminated {
+        context
+            .report_at(
+                DiagnosticCode::UnclosedBlock,
+                "Conditional block is never closed",
+                line,
+            )
+            .with_suggestion(format!("Close the block with `{}`", syntax::blocks::MARKER));
+    }
+
+    Some(DialogueStep::Conditional {
+        condition,
+        then_steps,
+        else_steps,
+        location: None,
+    })
 }
 
-/// Determines if a line starts a new parsing step rather than continuation text.
+/// Parses a loop block, which plays its steps repeatedly.
 ///
-/// This function is crucial for the parser to know when a line should be treated
-/// as a new dialogue element vs. continuation of the previous element.
+/// # Syntax
+/// - `~ REPEAT count` - Plays the steps a fixed number of times
+/// - `~ WHILE condition` - Plays the steps for as long as the condition is truthy
+/// - `~ EACH $array as $item` - Plays the steps once per array entry, binding it to `$item`
 ///
-/// # New Step Indicators
-/// - Empty lines
-/// - Comments: `//`, `///`, `//?`, `//!`
-/// - Actors: `@actor_name`
-/// - Sections: `# section_name`
-/// - Functions: `!function_name`
-/// - Variables: `$variable_name`
-/// - Navigation: `=><=`, `=>`
+/// Each loop block is closed with a lone `~`.
 ///
 /// # Example
+/// ```text
+/// ~ EACH $inventory as $item
+/// You check your bag.
+/// ~
 /// ```
-/// This is regular text
-/// This continues the same text block
-/// // This starts a new comment step
-/// This would be regular text again
-/// @speaker: This starts speaker dialogue
-/// ```
-#[inline]
-fn is_new_step(line: &str) -> bool {
-    if line.is_empty() {
-        return true;
-    }
+///
+/// # Notes
+/// - Generates a warning if the repeat count is not a whole number
+/// - The `$item` variable of an `EACH` loop is only defined within the loop's steps
+fn parse_loop(
+    line: &str,
+    lines: &mut SourceLines,
+    context: &mut ParseContext,
+) -> Option<DialogueStep> {
+    let header = parse_block_marker(line)??;
+
+    let (keyword, arguments) = header
+        .split_once(char::is_whitespace)
+        .map_or((header, ""), |(keyword, arguments)| {
+            (keyword, arguments.trim())
+        });
+
+    let new_step = if keyword.eq_ignore_ascii_case(syntax::blocks::REPEAT) {
+        let count = arguments.parse::<usize>().unwrap_or_else(|_| {
+            context.report_at(
+                DiagnosticCode::InvalidRepeatCount,
+                format!("Invalid repeat count ({arguments})"),
+                arguments,
+            );
+            0
+        });
+
+        DialogueStep::Repeat {
+            count,
+            steps: parse_loop_steps(line, lines, context),
+            location: None,
+        }
+    } else if keyword.eq_ignore_ascii_case(syntax::blocks::WHILE) {
+        DialogueStep::While {
+            condition: parse_expression(arguments, context),
+            steps: parse_loop_steps(line, lines, context),
+            location: None,
+        }
+    } else if keyword.eq_ignore_ascii_case(syntax::blocks::EACH) {
+        let (array, item) = parse_each_arguments(arguments, context);
+
+        context.scoped_variables.push(item.clone());
+        let steps = parse_loop_steps(line, lines, context);
+        context.scoped_variables.pop();
+
+        DialogueStep::Each {
+            array,
+            item,
+            steps,
+            location: None,
+        }
+    } else {
+        return None;
+    };
 
-    // Check for all the prefixes that indicate new parsing steps
-    line.starts_with(syntax::comments::BASIC) ||  // Includes ///, //?, //!
-        line.starts_with(syntax::prefixes::ACTOR) ||
-        line.starts_with(syntax::prefixes::SECTION) ||
-        line.starts_with(syntax::prefixes::FUNCTION) ||
-        line.starts_with(syntax::prefixes::VARIABLE) ||
-        line.starts_with(syntax::navigation::BOUNCE) ||
-        line.starts_with(syntax::navigation::JUMP)
+    Some(new_step)
+}
+
+/// Parses the steps of a loop block following its header line, as loops have no alternate
+/// branch.
+fn parse_loop_steps(
+    header_line: &str,
+    lines: &mut SourceLines,
+    context: &mut ParseContext,
+) -> Vec<DialogueStep> {
+    let (mut steps, mut block_end) = parse_block_steps(lines, context);
+
+    // Keep any steps after a misplaced else marker as part of the loop
+    while block_end == BlockEnd::Else {
+        let block_span = context.span_of(header_line);
+
+        context
+            .report(
+                DiagnosticCode::LoopElse,
+                "Loop blocks cannot have an else branch",
+            )
+            .with_related(block_span, "loop block starts here");
+
+        let (more_steps, next_block_end) = parse_block_steps(lines, context);
+
+        steps.extend(more_steps);
+        block_end = next_block_end;
+    }
+
+    if block_end == BlockEnd::Unterminated {
+        context
+            .report_at(
+                DiagnosticCode::UnclosedBlock,
+                "Loop block is never closed",
+                header_line,
+            )
+            .with_suggestion(format!("Close the block with `{}`", syntax::blocks::MARKER));
+    }
+
+    steps
+}
+
+/// Parses the array and item variable names of an `EACH` loop.
+///
+/// # Syntax
+/// `$array as $item`
+///
+/// # Notes
+/// - Generates an error if the array or item is missing
+/// - Generates a warning if either side is not a variable
+/// - Generates a warning if the array variable hasn't been defined or is not an array, unless the
+///   loop is already malformed
+fn parse_each_arguments(from: &str, context: &mut ParseContext) -> (String, String) {
+    // The binding keyword stands alone, so variables containing it aren't split
+    let binding = from
+        .match_indices(syntax::blocks::EACH_BINDING)
+        .find(|(index, keyword)| {
+            let before = from[..*index].chars().next_back();
+            let after = from[index + keyword.len()..].chars().next();
+
+            before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace)
+        });
+
+    let (raw_array, raw_item) = match binding {
+        Some((index, keyword)) => (from[..index].trim(), from[index + keyword.len()..].trim()),
+        None => (from.trim(), ""),
+    };
+
+    let missing = match (raw_array.is_empty(), raw_item.is_empty()) {
+        (true, true) => Some("an array and item"),
+        (true, false) => Some("an array"),
+        (false, true) => Some("an item"),
+        (false, false) => None,
+    };
+
+    if let Some(missing) = missing {
+        context
+            .report(
+                DiagnosticCode::MissingValue,
+                format!("Loop is missing {missing}"),
+            )
+            .with_suggestion(format!(
+                "Loop over an array: `{} {} $array {} $item`",
+                syntax::blocks::MARKER,
+                syntax::blocks::EACH,
+                syntax::blocks::EACH_BINDING
+            ));
+    }
+
+    let mut malformed = missing.is_some();
+
+    let mut parse_name = |raw: &str| {
+        if raw.is_empty() {
+            return String::new();
+        }
+
+        let name = raw
+            .strip_prefix(syntax::prefixes::VARIABLE)
+            .map(str::trim_start)
+            .filter(|name| !name.contains(char::is_whitespace));
+
+        let Some(name) = name else {
+            context.report_at(
+                DiagnosticCode::ExpectedVariable,
+                format!("Expected a variable in loop ({raw})"),
+                raw,
+            );
+            malformed = true;
+            return raw.to_lowercase();
+        };
+
+        name.to_lowercase()
+    };
+
+    let array = parse_name(raw_array);
+    let item = parse_name(raw_item);
+
+    // Malformed headers have already been reported, so aren't reported again as undefined
+    if malformed {
+        return (array, item);
+    }
+
+    match context.dialogue.variables.get(&array) {
+        Some(DialogueValue::Array(_)) => {}
+        Some(_) => {
+            context.report_at(
+                DiagnosticCode::NotAnArray,
+                format!("Loop variable is not an array [{array}]"),
+                raw_array,
+            );
+        }
+        None if context.has_variable(&array) => {}
+        None => report_undefined_variable(&array, raw_array, context),
+    }
+
+    (array, item)
+}
+
+/// Parses the header of a conditional block, returning its raw condition.
+///
+/// # Syntax
+/// `[if=condition]`
+fn parse_conditional_header(line: &str) -> Option<&str> {
+    line.strip_prefix(syntax::annotations::START)
+        .and_then(|line| line.strip_suffix(syntax::annotations::END))
+        .and_then(|line| line.split_once(syntax::delimiters::ASSIGNMENT))
+        .filter(|(key, _)| key.trim().eq_ignore_ascii_case(syntax::annotations::IF))
+        .map(|(_, condition)| condition.trim())
+}
+
+/// Parses the steps within a block until its closing marker.
+///
+/// Parsing stops without consuming the line when a new section starts, as blocks cannot span
+/// multiple sections.
+fn parse_block_steps(
+    lines: &mut SourceLines,
+    context: &mut ParseContext,
+) -> (Vec<DialogueStep>, BlockEnd) {
+    let mut steps = Vec::new();
+
+    while let Some(&(line_index, raw_next_line)) = lines.peek() {
+        let next_line = raw_next_line.trim();
+
+        if parse_section(next_line).is_some() {
+            break;
+        }
+
+        lines.next();
+        context.current_line = line_index + 1;
+
+        if next_line.is_empty() {
+            continue;
+        }
+
+        match parse_block_marker(next_line) {
+            Some(None) => return (steps, BlockEnd::Close),
+            Some(Some(keyword)) if keyword.eq_ignore_ascii_case(syntax::blocks::ELSE) => {
+                return (steps, BlockEnd::Else);
+            }
+            _ => {}
+        }
+
+        if let Some(step) = parse_step(raw_next_line, lines, context) {
+            steps.push(step);
+        }
+    }
+
+    (steps, BlockEnd::Unterminated)
+}
+
+/// Parses the steps nested under a response, which are indented further than the response.
+///
+/// Parsing stops without consuming the line at the first non-blank line which is indented no
+/// further than the response, or which starts a new section.
+///
+/// # Example
+/// ```text
+/// - Open the door
+///     The door creaks open.
+///     => hallway
+/// - Leave it
+///     - Are you sure?
+///         You walk away.
+/// ```
+fn parse_nested_steps(
+    lines: &mut SourceLines,
+    context: &mut ParseContext,
+    parent_indentation: usize,
+) -> Vec<DialogueStep> {
+    let mut steps = Vec::new();
+
+    while let Some(raw_next_line) = lines
+        .clone()
+        .map(|(_, line)| line)
+        .find(|line| !line.trim().is_empty())
+    {
+        if get_indentation(raw_next_line) <= parent_indentation
+            || parse_section(raw_next_line.trim()).is_some()
+        {
+            break;
+        }
+
+        // Skip over any blank lines before the nested line
+        let Some((line_index, raw_next_line)) = lines.find(|(_, line)| !line.trim().is_empty())
+        else {
+            break;
+        };
+
+        context.current_line = line_index + 1;
+
+        if let Some(step) = parse_step(raw_next_line, lines, context) {
+            steps.push(step);
+        }
+    }
+
+    steps
+}
+
+/// Parses a block marker line, returning its keyword if it has one.
+///
+/// # Syntax
+/// - `~` - Closes the current block
+/// - `~ KEYWORD` - Marks a point within a block, such as `~ ELSE`
+fn parse_block_marker(line: &str) -> Option<Option<&str>> {
+    let keyword = line.strip_prefix(syntax::blocks::MARKER)?.trim();
+
+    Some((!keyword.is_empty()).then_some(keyword))
+}
+
+// =====================================
+// Expression Parsing Functions
+// =====================================
+
+/// A single token of an expression, split by whitespace.
+#[derive(Clone, Debug, PartialEq)]
+enum ExpressionToken<'a> {
+    /// A literal value, such as `10`, `true`, `"quoted text"` or `[an, array]`
+    Value(DialogueValue),
+    /// A bare word which is part of unquoted text
+    Word(String),
+    /// A variable or actor property reference, such as `$gold` or `@oscar.age`
+    Reference(&'a str),
+    Binary(BinaryOperator),
+    Not,
+    GroupStart,
+    GroupEnd,
+}
+
+/// Tokens of an expression being parsed
+type ExpressionTokens<'a> = Peekable<std::vec::IntoIter<ExpressionToken<'a>>>;
+
+/// Binary operators and their symbols, used to recognise operator tokens.
+const BINARY_OPERATORS: [(&str, BinaryOperator); 13] = [
+    (syntax::operators::ADD, BinaryOperator::Add),
+    (syntax::operators::SUBTRACT, BinaryOperator::Subtract),
+    (syntax::operators::MULTIPLY, BinaryOperator::Multiply),
+    (syntax::operators::DIVIDE, BinaryOperator::Divide),
+    (syntax::operators::REMAINDER, BinaryOperator::Remainder),
+    (syntax::operators::EQUAL, BinaryOperator::Equal),
+    (syntax::operators::NOT_EQUAL, BinaryOperator::NotEqual),
+    (syntax::operators::LESS, BinaryOperator::Less),
+    (
+        syntax::operators::LESS_OR_EQUAL,
+        BinaryOperator::LessOrEqual,
+    ),
+    (syntax::operators::GREATER, BinaryOperator::Greater),
+    (
+        syntax::operators::GREATER_OR_EQUAL,
+        BinaryOperator::GreaterOrEqual,
+    ),
+    (syntax::operators::AND, BinaryOperator::And),
+    (syntax::operators::OR, BinaryOperator::Or),
+];
+
+/// Binding strength of `not`, which applies to whole comparisons: `not $gold > 10`
+const NOT_PRECEDENCE: u8 = 3;
+
+/// Parses an expression for use by variable assignments, conditional blocks and loop blocks.
+///
+/// Expressions combine values, references and operators, and are evaluated when the dialogue is
+/// played.
+///
+/// # Syntax
+/// - `value` - A literal value, parsed as per variable definitions
+/// - `"quoted text"` - A literal text value, which may contain operators and keywords
+/// - `$variable_name` - The variable's current value
+/// - `@actor.property` - The actor property's value
+/// - `left operator right` - A binary operation
+/// - `not operand`, `- operand` - A unary operation
+/// - `(expression)` - Groups an expression to be evaluated first
+///
+/// # Operators
+/// From loosest to tightest binding:
+/// 1. `or`
+/// 2. `and`
+/// 3. `not`
+/// 4. `==`, `!=`, `<`, `<=`, `>`, `>=`
+/// 5. `+`, `-`
+/// 6. `*`, `/`, `%`
+///
+/// # Example
+/// ```text
+/// $gold = $gold + 10
+/// $ok = $a and not $b
+/// $greeting = "Hello, " + @oscar.name
+/// [if=$gold >= 10 or $has_key]
+/// ~ WHILE ($gold - 5) * 2 < 100
+/// ```
+///
+/// # Notes
+/// - Operators must be separated from their operands by whitespace
+/// - Text without any references or quotes is kept as a literal value, so `Salt and Pepper`
+///   remains text, while `$name and Pepper` is treated as an expression
+/// - Generates a warning if a referenced variable or actor property hasn't been defined
+/// - Generates a warning if an expression with references or operators cannot be parsed, in
+///   which case the whole expression is treated as a literal value
+fn parse_expression(from: &str, context: &mut ParseContext) -> DialogueExpression {
+    let tokens = tokenize_expression(from);
+
+    let has_references_or_quotes = tokens.iter().any(|token| {
+        matches!(
+            token,
+            ExpressionToken::Reference(_) | ExpressionToken::Value(DialogueValue::Text(_))
+        )
+    });
+    let has_words = tokens
+        .iter()
+        .any(|token| matches!(token, ExpressionToken::Word(_)));
+
+    // Plain unquoted text is a literal value, just like a variable definition
+    if !has_references_or_quotes && (has_words || tokens.len() <= 1) {
+        return DialogueExpression::Value(parse_value(from));
+    }
+
+    let mut tokens = tokens.into_iter().peekable();
+    let expression = parse_expression_binary(&mut tokens, 1, context);
+
+    match expression {
+        Some(expression) if tokens.peek().is_none() => expression,
+        _ => {
+            context
+                .report_at(
+                    DiagnosticCode::InvalidExpression,
+                    format!("Invalid expression ({from})"),
+                    from,
+                )
+                .with_suggestion(
+                    "Separate operators from their operands with whitespace, and quote any text \
+                     containing operators",
+                );
+
+            DialogueExpression::Value(parse_value(from))
+        }
+    }
+}
+
+/// Splits an expression into tokens, keeping quoted text, arrays and groups intact.
+fn tokenize_expression(from: &str) -> Vec<ExpressionToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut remaining = from.trim_start();
+
+    while !remaining.is_empty() {
+        // Check for quoted text
+        // `"some text"`
+        if let Some(quoted) = remaining.strip_prefix(syntax::delimiters::QUOTE)
+            && let Some((text, rest)) = quoted.split_once(syntax::delimiters::QUOTE)
+        {
+            tokens.push(ExpressionToken::Value(DialogueValue::Text(
+                text.to_string(),
+            )));
+            remaining = rest.trim_start();
+            continue;
+        }
+
+        // Check for arrays
+        // `[first item, second item]`
+        if remaining.starts_with(syntax::delimiters::ARRAY_START)
+            && let Some(array_end) = remaining.find(syntax::delimiters::ARRAY_END)
+        {
+            let (array, rest) = remaining.split_at(array_end + syntax::delimiters::ARRAY_END.len());
+            tokens.push(ExpressionToken::Value(parse_value(array)));
+            remaining = rest.trim_start();
+            continue;
+        }
+
+        if let Some(rest) = remaining.strip_prefix(syntax::delimiters::GROUP_START) {
+            tokens.push(ExpressionToken::GroupStart);
+            remaining = rest.trim_start();
+            continue;
+        }
+
+        let chunk_end = remaining
+            .find(char::is_whitespace)
+            .unwrap_or(remaining.len());
+        let (chunk, rest) = remaining.split_at(chunk_end);
+        remaining = rest.trim_start();
+
+        // Closing groups may directly follow an operand: `($gold + 10)`
+        let operand = chunk.trim_end_matches(syntax::delimiters::GROUP_END);
+        let group_ends = (chunk.len() - operand.len()) / syntax::delimiters::GROUP_END.len();
+
+        if !operand.is_empty() {
+            tokens.push(parse_expression_token(operand));
+        }
+
+        tokens.extend(std::iter::repeat_n(ExpressionToken::GroupEnd, group_ends));
+    }
+
+    tokens
+}
+
+/// Parses a single whitespace-separated token of an expression.
+fn parse_expression_token(from: &str) -> ExpressionToken<'_> {
+    if let Some((_, operator)) = BINARY_OPERATORS.iter().find(|(symbol, _)| *symbol == from) {
+        return ExpressionToken::Binary(*operator);
+    }
+
+    if from == syntax::operators::NOT {
+        return ExpressionToken::Not;
+    }
+
+    if from.starts_with(syntax::prefixes::VARIABLE) || from.starts_with(syntax::prefixes::ACTOR) {
+        return ExpressionToken::Reference(from);
+    }
+
+    match parse_value(from) {
+        DialogueValue::Text(word) => ExpressionToken::Word(word),
+        value => ExpressionToken::Value(value),
+    }
+}
+
+/// Parses a chain of binary operations which bind at least as tightly as the given precedence.
+fn parse_expression_binary(
+    tokens: &mut ExpressionTokens,
+    min_precedence: u8,
+    context: &mut ParseContext,
+) -> Option<DialogueExpression> {
+    let mut left = parse_expression_operand(tokens, context)?;
+
+    while let Some(&ExpressionToken::Binary(operator)) = tokens.peek() {
+        let precedence = get_operator_precedence(operator);
+
+        if precedence < min_precedence {
+            break;
+        }
+
+        tokens.next();
+        let right = parse_expression_binary(tokens, precedence + 1, context)?;
+
+        left = DialogueExpression::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        };
+    }
+
+    Some(left)
+}
+
+/// Parses a single operand of an expression, including any unary operators applied to it.
+fn parse_expression_operand(
+    tokens: &mut ExpressionTokens,
+    context: &mut ParseContext,
+) -> Option<DialogueExpression> {
+    match tokens.next()? {
+        ExpressionToken::Value(value) => Some(DialogueExpression::Value(value)),
+
+        ExpressionToken::Reference(reference) => {
+            let expression = match parse_reference(reference, context) {
+                Some(TextSegment::Variable(name)) => DialogueExpression::Variable(name),
+                Some(TextSegment::ActorProperty { actor, property }) => {
+                    DialogueExpression::ActorProperty { actor, property }
+                }
+                _ => DialogueExpression::Value(DialogueValue::Text(reference.to_string())),
+            };
+
+            Some(expression)
+        }
+
+        // Consecutive words form a single piece of text
+        ExpressionToken::Word(mut text) => {
+            while let Some(ExpressionToken::Word(word)) = tokens.peek() {
+                text = format!("{text} {word}");
+                tokens.next();
+            }
+
+            Some(DialogueExpression::Value(DialogueValue::Text(text)))
+        }
+
+        ExpressionToken::Not => Some(DialogueExpression::Unary {
+            operator: UnaryOperator::Not,
+            operand: Box::new(parse_expression_binary(tokens, NOT_PRECEDENCE, context)?),
+        }),
+
+        ExpressionToken::Binary(BinaryOperator::Subtract) => Some(DialogueExpression::Unary {
+            operator: UnaryOperator::Negate,
+            operand: Box::new(parse_expression_operand(tokens, context)?),
+        }),
+
+        ExpressionToken::GroupStart => {
+            let expression = parse_expression_binary(tokens, 1, context)?;
+
+            (tokens.next() == Some(ExpressionToken::GroupEnd)).then_some(expression)
+        }
+
+        ExpressionToken::Binary(_) | ExpressionToken::GroupEnd => None,
+    }
+}
+
+/// Returns how tightly a binary operator binds, where higher values bind more tightly.
+fn get_operator_precedence(operator: BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Or => 1,
+        BinaryOperator::And => 2,
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::Less
+        | BinaryOperator::LessOrEqual
+        | BinaryOperator::Greater
+        | BinaryOperator::GreaterOrEqual => 4,
+        BinaryOperator::Add | BinaryOperator::Subtract => 5,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Remainder => 6,
+    }
+}
+
+// =====================================
+// Utility Functions
+// =====================================
+
+/// Handles include directives when parsing without a project loader, by reporting them.
+fn report_unresolved_include(path: &str, context: &mut ParseContext) -> Option<Dialogue> {
+    context
+        .report_at(
+            DiagnosticCode::UnresolvedInclude,
+            format!("Include cannot be resolved outside of a project [{path}]"),
+            path,
+        )
+        .with_suggestion("Load the dialogue as a project to resolve includes");
+
+    None
+}
+
+/// A definition which replaces an earlier definition of the same name.
+#[derive(Debug)]
+pub(crate) struct Redefinition<'a> {
+    /// Kind of definition, such as `Actor`
+    pub kind: &'static str,
+    pub name: &'a str,
+    pub location: &'a SourceLocation,
+    pub previous: &'a SourceLocation,
+}
+
+impl Redefinition<'_> {
+    /// Creates a diagnostic for the redefinition at the given span of a source, pointing at both
+    /// definitions.
+    pub fn to_diagnostic(
+        &self,
+        span: SourceSpan,
+        file: Option<&str>,
+        source: &SourceMap,
+    ) -> Diagnostic {
+        let related_span = |location: &SourceLocation| {
+            if location.file.as_deref() == file {
+                return source.line_span(location.line);
+            }
+
+            SourceSpan {
+                line: location.line,
+                column: location.column,
+                ..SourceSpan::default()
+            }
+        };
+
+        let mut diagnostic = Diagnostic::new(
+            DiagnosticCode::DuplicateDefinition,
+            format!("{} is already defined [{}]", self.kind, self.name),
+            span,
+        );
+        diagnostic.file = file.map(str::to_string);
+
+        diagnostic.with_related_in(
+            self.previous.file.clone(),
+            related_span(self.previous),
+            "first defined here",
+        );
+
+        // Included definitions are reported at the include directive
+        if self.location.file.as_deref() != file || self.location.line != span.line {
+            diagnostic.with_related_in(
+                self.location.file.clone(),
+                related_span(self.location),
+                "defined again here",
+            );
+        }
+
+        diagnostic.with_suggestion("Rename or remove one of the definitions");
+        diagnostic
+    }
+}
+
+/// Finds the definitions of a dialogue which would replace those of the same name in an existing
+/// dialogue when merged into it, ordered by where they're defined.
+///
+/// Definitions without source locations, or at the same location as the existing definition,
+/// aren't counted, as the same file's definitions may be merged more than once.
+pub(crate) fn find_redefinitions<'a>(
+    existing: &'a Dialogue,
+    definitions: &'a Dialogue,
+) -> Vec<Redefinition<'a>> {
+    let actors = definitions.actors.iter().map(|(name, actor)| {
+        let previous = existing.actors.get(name);
+        let previous = previous.and_then(|actor| actor.location.as_ref());
+
+        ("Actor", name, actor.location.as_ref(), previous)
+    });
+
+    let functions = definitions.functions.iter().map(|(name, function)| {
+        let previous = existing.functions.get(name);
+        let previous = previous.and_then(|function| function.location.as_ref());
+
+        ("Function", name, function.location.as_ref(), previous)
+    });
+
+    let variables = definitions
+        .variable_locations
+        .iter()
+        .map(|(name, location)| {
+            let previous = existing.variable_locations.get(name);
+
+            ("Variable", name, Some(location), previous)
+        });
+
+    let mut redefinitions = actors
+        .chain(functions)
+        .chain(variables)
+        .filter_map(|(kind, name, location, previous)| {
+            let (location, previous) = (location?, previous?);
+
+            (location != previous).then_some(Redefinition {
+                kind,
+                name,
+                location,
+                previous,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    redefinitions.sort_by_key(|redefinition| (redefinition.location.line, redefinition.name));
+    redefinitions
+}
+
+/// Reports a definition at the given location replacing an earlier one of the same name from the
+/// same file.
+///
+/// Definitions replacing those from other files are reported as the project loads, see
+/// [`ProjectLoader`].
+fn check_duplicate_definition(
+    kind: &'static str,
+    name: &str,
+    previous: Option<&SourceLocation>,
+    location: &SourceLocation,
+    context: &mut ParseContext,
+) {
+    let Some(previous) = previous.filter(|previous| previous.file.as_deref() == context.file)
+    else {
+        return;
+    };
+
+    report_duplicate_definition(
+        &Redefinition {
+            kind,
+            name,
+            location,
+            previous,
+        },
+        context,
+    );
+}
+
+/// Reports a definition replacing an earlier one of the same name from the file being parsed,
+/// on the current line.
+///
+/// The current line is either the new definition, or the include directive it was included by.
+fn report_duplicate_definition(redefinition: &Redefinition, context: &mut ParseContext) {
+    let span = context.source.line_span(context.current_line);
+    let diagnostic = redefinition.to_diagnostic(span, context.file, context.source);
+
+    context.push_diagnostic(diagnostic);
+}
+
+/// Reports a reference to a variable which hasn't been defined, at the given slice of source.
+fn report_undefined_variable(name: &str, at: &str, context: &mut ParseContext) {
+    context
+        .report_at(
+            DiagnosticCode::UndefinedVariable,
+            format!("Static variable definition not found [{name}]"),
+            at,
+        )
+        .with_suggestion(format!(
+            "Define the variable first: `{}{name}{} value`",
+            syntax::prefixes::VARIABLE,
+            syntax::delimiters::SEPARATOR
+        ));
+}
+
+/// Reports a reference to an actor which hasn't been defined, at the given slice of source.
+fn report_undefined_actor(actor_id: &str, at: &str, context: &mut ParseContext) {
+    context
+        .report_at(
+            DiagnosticCode::UndefinedActor,
+            format!("Actor definition not found ({actor_id})"),
+            at,
+        )
+        .with_suggestion(format!(
+            "Define the actor first: `{}{actor_id}`",
+            syntax::prefixes::ACTOR
+        ));
+}
+
+/// Converts a string value into the appropriate DialogueValue type.
+///
+/// This function attempts to parse the input string as different data types
+/// in order of specificity: boolean, number, array, then defaults to text.
+///
+/// # Type Detection Order
+/// 1. **Boolean**: `true` or `false` (case-sensitive)
+/// 2. **Number**: Any valid f64 (e.g., `42`, `3.14`, `-1.5`)
+/// 3. **Array**: Comma-separated values in brackets `[item1, item2, item3]`
+/// 4. **Text**: Everything else (default)
+///
+/// # Examples
+/// ```text
+/// parse_value("true")        // -> DialogueValue::Boolean(true)
+/// parse_value("42")          // -> DialogueValue::Number(42.0)
+/// parse_value("3.14")        // -> DialogueValue::Number(3.14)
+/// parse_value("[a, b, c]")   // -> DialogueValue::Array(vec!["a", "b", "c"])
+/// parse_value("hello")       // -> DialogueValue::Text("hello")
+/// parse_value("not_a_bool")  // -> DialogueValue::Text("not_a_bool")
+/// ```
+fn parse_value(from: &str) -> DialogueValue {
+    // Check for boolean value
+    if let Ok(bool_value) = str::parse::<bool>(from) {
+        return DialogueValue::Boolean(bool_value);
+    }
+
+    // Check for numeric value
+    if let Ok(number_value) = str::parse::<f64>(from) {
+        return DialogueValue::Number(number_value);
+    }
+
+    // Check for array of unquoted strings
+    // `[first item, second item, third item]`
+    if let Some(comma_separated_values) = from
+        .strip_prefix(syntax::delimiters::ARRAY_START)
+        .and_then(|s| s.strip_suffix(syntax::delimiters::ARRAY_END))
+    {
+        let values: Vec<String> = comma_separated_values
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        return DialogueValue::Array(values);
+    }
+
+    // Default to textual value
+    DialogueValue::Text(from.to_string())
+}
+
+/// Determines if a line starts a new parsing step rather than continuation text.
+///
+/// This function is crucial for the parser to know when a line should be treated
+/// as a new dialogue element vs. continuation of the previous element.
+///
+/// # New Step Indicators
+/// - Empty lines
+/// - Comments: `//`, `///`, `//?`, `//!`
+/// - Actors: `@actor_name`
+/// - Sections: `# section_name`
+/// - Functions: `!function_name`
+/// - Variables: `$variable_name`
+/// - Navigation: `=><=`, `=>`
+/// - Blocks: `[if=condition]`, `~`, `~ KEYWORD`
+///
+/// # Example
+/// ```text
+/// This is regular text
+/// This continues the same text block
+/// // This starts a new comment step
+/// This would be regular text again
+/// @speaker: This starts speaker dialogue
+/// ```
+#[inline]
+fn is_new_step(line: &str) -> bool {
+    if line.is_empty() {
+        return true;
+    }
+
+    // Check for all the prefixes that indicate new parsing steps
+    line.starts_with(syntax::comments::BASIC) ||  // Includes ///, //?, //!
+        line.starts_with(syntax::prefixes::ACTOR) ||
+        line.starts_with(syntax::prefixes::SECTION) ||
+        line.starts_with(syntax::prefixes::FUNCTION) ||
+        line.starts_with(syntax::prefixes::VARIABLE) ||
+        line.starts_with(syntax::navigation::BOUNCE) ||
+        line.starts_with(syntax::navigation::JUMP) ||
+        line.starts_with(syntax::annotations::START) ||
+        line.starts_with(syntax::blocks::MARKER) ||
+        parse_include(line).is_some()
+}
+
+/// Gets the indentation of a line, counting each leading whitespace character once.
+#[inline]
+fn get_indentation(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+/// Determines if a line is spoken by an actor, rather than defining one.
+///
+/// # Example
+/// ```text
+/// @Oscar: This is spoken
+/// @Oscar
+/// ```
+#[inline]
+fn is_spoken_line(line: &str) -> bool {
+    line.strip_prefix(syntax::prefixes::ACTOR)
+        .and_then(|line| line.split_once(syntax::delimiters::SEPARATOR))
+        .is_some_and(|(_, text)| !text.trim().is_empty())
 }
 
 // =====================================
 // Complex Element Parsing Functions
 // =====================================
 
-fn parse_actor_definition(
-    line: &str,
-    lines: &mut Peekable<Lines>,
-) -> Option<(String, DialogueActor)> {
+fn parse_actor_definition(line: &str, lines: &mut SourceLines) -> Option<(String, DialogueActor)> {
     let actor_definition = line.strip_prefix(syntax::prefixes::ACTOR)?;
 
     // Ensure not parsing a spoken line
@@ -470,7 +1797,7 @@ fn parse_actor_definition(
     let mut properties = HashMap::new();
 
     // Sub-iterate over subsequent lines
-    while let Some(&next_line) = lines.peek() {
+    while let Some(&(_, next_line)) = lines.peek() {
         // If we hit a new step, break out of the sub-iteration
         if is_new_step(next_line) {
             break;
@@ -479,7 +1806,7 @@ fn parse_actor_definition(
         // Parse the next line as a property, cancel sub-iteration if not possible
         let Some((property_name, property_value_raw)) = lines
             .next()
-            .and_then(|line| line.split_once(syntax::delimiters::SEPARATOR))
+            .and_then(|(_, line)| line.split_once(syntax::delimiters::SEPARATOR))
         else {
             break;
         };
@@ -500,6 +1827,7 @@ fn parse_actor_definition(
         DialogueActor {
             name: display_name.to_string(),
             properties,
+            location: None,
         },
     ))
 }
@@ -517,13 +1845,21 @@ fn parse_actor_definition(
 /// - `!function_name(params): return_value` - Full syntax with params and return
 ///
 /// # Examples
-/// ```
+/// ```text
 /// !save_game
 /// !play_sound(file=bell.wav, volume=0.8)
 /// !get_player_name: Unknown
 /// !check_inventory(item=key): false
 /// ```
-fn parse_function_definition(line: &str) -> Option<(String, DialogueFunction)> {
+///
+/// # Notes
+/// - Functions can only be defined before the first section, see
+///   [`report_misplaced_function_definition`]
+/// - Generates an error if the argument list is never closed
+fn parse_function_definition(
+    line: &str,
+    context: &mut ParseContext,
+) -> Option<(String, DialogueFunction)> {
     let function_definition = line.strip_prefix(syntax::prefixes::FUNCTION)?;
 
     let mut function_text = function_definition;
@@ -544,20 +1880,14 @@ fn parse_function_definition(line: &str) -> Option<(String, DialogueFunction)> {
 
     // Check for arguments
     if let Some((function_name, arg_definitions)) = function_text.split_once('(') {
-        let arg_definitions = arg_definitions.trim_end_matches(')').trim();
+        let arg_definitions = strip_function_args_end(arg_definitions, context);
 
         // Parse arguments if any
         args = if !arg_definitions.is_empty() {
             Some(
-                arg_definitions
-                    .split(',')
-                    .map(|arg| {
-                        let mut parts = arg.splitn(2, syntax::delimiters::ASSIGNMENT);
-                        let name = parts.next().unwrap().trim().to_string();
-                        let value = parts.next().unwrap_or("").trim();
-
-                        (name, parse_value(value))
-                    })
+                parse_function_args(arg_definitions)
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value))
                     .collect(),
             )
         } else {
@@ -569,33 +1899,400 @@ fn parse_function_definition(line: &str) -> Option<(String, DialogueFunction)> {
 
     let function_name = function_text.trim().to_lowercase();
 
-    let new_function = DialogueFunction { args, result };
+    let new_function = DialogueFunction {
+        args,
+        result,
+        location: None,
+    };
 
     Some((function_name, new_function))
 }
 
+/// Parses function calls, which hand control to the game before continuing the dialogue.
+///
+/// # Syntax
+/// - `=><= !function_name(arg=value, ...)` - Calls the function, discarding any result
+/// - `$variable_name = !function_name(arg=value, ...)` - Calls the function, storing its result
+///
+/// Arguments are given by name, with any not given taking their defined default values.
+///
+/// # Example
+/// ```text
+/// !save_game(slot=1)
+/// !get_player_name: Unknown
+///
+/// =><= !save_game(slot=2)
+/// $player_name = !get_player_name()
+/// ```
+fn parse_function_call_step(line: &str, context: &mut ParseContext) -> Option<DialogueStep> {
+    let call_text = line
+        .strip_prefix(syntax::navigation::BOUNCE)
+        .and_then(|line| line.trim().strip_prefix(syntax::prefixes::FUNCTION))?;
+
+    Some(parse_function_call(call_text, None, context))
+}
+
+/// Reports a function definition within a section, which is most likely meant as a call.
+///
+/// # Notes
+/// - Generates an error, suggesting the function be called instead
+fn report_misplaced_function_definition(function_definition: &str, context: &mut ParseContext) {
+    let function_name = function_definition
+        .split(['(', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    context
+        .report(
+            DiagnosticCode::MisplacedDefinition,
+            format!("Functions must be defined before the first section ({function_name})"),
+        )
+        .with_suggestion(format!(
+            "Call the function with `{} {}{function_name}()`, or move its definition before the first section",
+            syntax::navigation::BOUNCE,
+            syntax::prefixes::FUNCTION
+        ));
+}
+
+/// Parses the function name and arguments of a function call, following its prefix.
+///
+/// # Notes
+/// - Generates a warning if the function hasn't been defined
+/// - Generates a warning if an argument isn't defined by the function or has a different type
+/// - Generates a warning if a result is stored from a function without a defined result
+fn parse_function_call(
+    call_text: &str,
+    result: Option<String>,
+    context: &mut ParseContext,
+) -> DialogueStep {
+    let (function_name, args) = match call_text.split_once('(') {
+        Some((function_name, arg_text)) => {
+            let arg_text = strip_function_args_end(arg_text, context);
+
+            let args = if arg_text.is_empty() {
+                Vec::new()
+            } else {
+                parse_function_args(arg_text)
+            };
+
+            (function_name, args)
+        }
+        None => (call_text, Vec::new()),
+    };
+
+    let raw_function_name = function_name.trim();
+    let function_name = raw_function_name.to_lowercase();
+    let dialogue = context.dialogue;
+
+    match dialogue.functions.get(&function_name) {
+        Some(function) => {
+            for (arg_name, arg_value) in &args {
+                match function.args.as_ref().and_then(|args| args.get(*arg_name)) {
+                    Some(default_value) if default_value.is_same_type(arg_value) => {}
+                    Some(default_value) => {
+                        context
+                            .report_at(
+                                DiagnosticCode::ArgumentTypeMismatch,
+                                format!(
+                                    "Function argument type does not match definition \
+                                     ({function_name}.{arg_name})"
+                                ),
+                                arg_name,
+                            )
+                            .with_suggestion(format!(
+                                "Use a value like the definition's default: `{default_value}`"
+                            ));
+                    }
+                    None => {
+                        context.report_at(
+                            DiagnosticCode::UndefinedArgument,
+                            format!("Function argument not found ({function_name}.{arg_name})"),
+                            arg_name,
+                        );
+                    }
+                }
+            }
+
+            if result.is_some() && function.result.is_none() {
+                context
+                    .report_at(
+                        DiagnosticCode::MissingResult,
+                        format!("Function has no result to store ({function_name})"),
+                        raw_function_name,
+                    )
+                    .with_suggestion(format!(
+                        "Give the function a default result: `{}{function_name}: value`",
+                        syntax::prefixes::FUNCTION
+                    ));
+            }
+        }
+
+        None => {
+            context
+                .report_at(
+                    DiagnosticCode::UndefinedFunction,
+                    format!("Function definition not found ({function_name})"),
+                    raw_function_name,
+                )
+                .with_suggestion(format!(
+                    "Define the function first: `{}{function_name}`",
+                    syntax::prefixes::FUNCTION
+                ));
+        }
+    }
+
+    DialogueStep::FunctionCall {
+        name: function_name,
+        args: args
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+        result,
+        location: None,
+    }
+}
+
+/// Strips the closing parenthesis from the arguments of a function definition or call.
+///
+/// # Notes
+/// - Generates an error if the closing parenthesis is missing, in which case the rest of the line
+///   is treated as arguments
+fn strip_function_args_end<'a>(arg_text: &'a str, context: &mut ParseContext) -> &'a str {
+    let arg_text = arg_text.trim();
+
+    match arg_text.strip_suffix(')') {
+        Some(arg_text) => arg_text.trim(),
+        None => {
+            context
+                .report_at(
+                    DiagnosticCode::UnclosedArguments,
+                    format!("Function arguments are never closed ({arg_text})"),
+                    arg_text,
+                )
+                .with_suggestion("Close the arguments with `)`");
+
+            arg_text
+        }
+    }
+}
+
+/// Parses a comma-separated list of named function arguments, in the order given.
+///
+/// # Syntax
+/// `arg_1=value, arg_2=value`
+fn parse_function_args(from: &str) -> Vec<(&str, DialogueValue)> {
+    from.split(',')
+        .map(|arg| {
+            let mut parts = arg.splitn(2, syntax::delimiters::ASSIGNMENT);
+            let name = parts.next().unwrap().trim();
+            let value = parts.next().unwrap_or("").trim();
+
+            (name, parse_value(value))
+        })
+        .collect()
+}
+
+/// Parses a page of dialogue lines, which are displayed together during playback.
+///
+/// A page continues until a blank line or a line starting a new step. Lines prefixed with the
+/// page extension marker always continue the page, allowing blank lines and lines which would
+/// otherwise start a new step to be kept on it.
+///
+/// # Syntax
+/// `| line` or `|`
+///
+/// # Example
+/// ```text
+/// | @Oscar: Hello!
+/// |
+/// | @Oscar: Are you still there?
+/// | - Yes
+/// | - No
+/// ```
 fn parse_page(
     line: &str,
-    lines: &mut Peekable<Lines>,
+    indentation: usize,
+    lines: &mut SourceLines,
     context: &mut ParseContext,
 ) -> Option<DialogueStep> {
     let mut page_lines = Vec::new();
+    let mut annotations = HashMap::new();
+
+    parse_page_line(
+        line,
+        indentation,
+        lines,
+        &mut page_lines,
+        &mut annotations,
+        context,
+    );
+
+    while let Some(&(line_index, raw_next_line)) = lines.peek() {
+        let next_line = raw_next_line.trim();
+        let next_indentation = get_indentation(raw_next_line);
+
+        // Annotations belong to the line after them, so they only continue the page if that line
+        // does, and that line continues the page if it is spoken text following annotations
+        let is_page_break = if next_indentation < indentation && !next_line.is_empty() {
+            true
+        } else if next_line.starts_with(syntax::prefixes::PAGE_EXTENSION) {
+            false
+        } else if parse_annotations(next_line).is_some() {
+            !page_lines.is_empty()
+                && lines
+                    .clone()
+                    .map(|(_, line)| line.trim())
+                    .find(|line| parse_annotations(line).is_none())
+                    .is_none_or(is_new_step)
+        } else {
+            is_new_step(next_line) && !(page_lines.is_empty() && is_spoken_line(next_line))
+        };
 
-    page_lines.push(parse_text_line(line, context));
-
-    while let Some(&next_line) = lines.peek() {
-        if is_new_step(next_line) {
+        if is_page_break {
             break;
         }
 
-        page_lines.push(parse_text_line(lines.next().unwrap(), context));
+        lines.next();
+        context.current_line = line_index + 1;
+
+        parse_page_line(
+            next_line,
+            next_indentation,
+            lines,
+            &mut page_lines,
+            &mut annotations,
+            context,
+        );
+    }
+
+    if !annotations.is_empty() {
+        context.report(
+            DiagnosticCode::DanglingAnnotations,
+            "Annotations are not followed by a line",
+        );
     }
 
     if page_lines.is_empty() {
         return None;
     }
 
-    Some(DialogueStep::Page(page_lines))
+    Some(DialogueStep::Page {
+        lines: page_lines,
+        location: None,
+    })
+}
+
+/// Parses a line within a page, either collecting its annotations or pushing it as a new line
+/// with any annotations collected before it.
+///
+/// Responses also consume the steps nested under them.
+fn parse_page_line(
+    line: &str,
+    indentation: usize,
+    lines: &mut SourceLines,
+    page_lines: &mut Vec<DialogueLine>,
+    annotations: &mut HashMap<String, DialogueValue>,
+    context: &mut ParseContext,
+) {
+    // Page extensions only mark the line as part of the page, with blank extensions kept purely
+    // for readability
+    let line = line
+        .strip_prefix(syntax::prefixes::PAGE_EXTENSION)
+        .map_or(line, str::trim);
+
+    if line.is_empty() {
+        return;
+    }
+
+    if let Some(line_annotations) = parse_annotations(line) {
+        annotations.extend(line_annotations);
+        return;
+    }
+
+    let mut page_line = parse_text_line(line, context);
+    page_line.set_location(Some(context.location_of(line)));
+
+    match &mut page_line {
+        DialogueLine::Text {
+            annotations: line_annotations,
+            ..
+        }
+        | DialogueLine::SpeakerText {
+            annotations: line_annotations,
+            ..
+        } => {
+            *line_annotations = std::mem::take(annotations);
+        }
+
+        DialogueLine::Response { pages, .. } => {
+            if !annotations.is_empty() {
+                context.report(
+                    DiagnosticCode::ResponseAnnotations,
+                    "Annotations cannot be applied to responses",
+                );
+
+                annotations.clear();
+            }
+
+            *pages = parse_nested_steps(lines, context, indentation);
+        }
+    }
+
+    page_lines.push(page_line);
+}
+
+/// Parses a line of annotations, which attach metadata to the dialogue line following them.
+///
+/// # Syntax
+/// `[key=value]` or `[key=value, other_key=other_value]`
+///
+/// Values are parsed as per variable definitions. Conditional block headers such as
+/// `[if=$variable]` are not annotations.
+///
+/// # Example
+/// ```text
+/// [mood=angry, volume=0.8]
+/// @Oscar: Get out!
+/// ```
+fn parse_annotations(line: &str) -> Option<HashMap<String, DialogueValue>> {
+    if parse_conditional_header(line).is_some() {
+        return None;
+    }
+
+    let annotation_list = line
+        .strip_prefix(syntax::annotations::START)?
+        .strip_suffix(syntax::annotations::END)?;
+
+    // Split on commas, except those within array values
+    let mut annotation_texts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, character) in annotation_list.char_indices() {
+        match character {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                annotation_texts.push(&annotation_list[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    annotation_texts.push(&annotation_list[start..]);
+
+    annotation_texts
+        .into_iter()
+        .map(|annotation| {
+            let (key, value) = annotation.split_once(syntax::delimiters::ASSIGNMENT)?;
+            let key = key.trim().to_lowercase();
+
+            (!key.is_empty()).then(|| (key, parse_value(value.trim())))
+        })
+        .collect()
 }
 
 fn parse_text_line(line: &str, context: &mut ParseContext) -> DialogueLine {
@@ -603,8 +2300,9 @@ fn parse_text_line(line: &str, context: &mut ParseContext) -> DialogueLine {
     // `- response text`
     if let Some(response_text) = line.strip_prefix(syntax::prefixes::RESPONSE) {
         return DialogueLine::Response {
-            text: response_text.trim().to_string(),
+            text: parse_text(response_text.trim(), context),
             pages: Vec::new(),
+            location: None,
         };
     }
 
@@ -612,30 +2310,337 @@ fn parse_text_line(line: &str, context: &mut ParseContext) -> DialogueLine {
     // `speaker: text` or `@speaker_id: text`
     if let Some((speaker, text)) = line.split_once(syntax::delimiters::SEPARATOR) {
         let speaker = speaker.trim();
-        let text = text.trim().to_string();
+        let text = text.trim();
 
         if !text.is_empty() {
-            let mut speaker = speaker.to_string();
-
             // Check if actor exists
-            if let Some(speaker_id) = speaker
-                .strip_prefix(syntax::prefixes::ACTOR)
-                .map(|speaker_id| speaker_id.trim().to_lowercase())
-            {
-                if !context.dialogue.actors.contains_key(speaker_id.as_str()) {
-                    context.warnings.push(format!(
-                        "Line {}: Actor definition not found ({})",
-                        context.current_line, speaker_id
-                    ));
+            let speaker = match speaker.strip_prefix(syntax::prefixes::ACTOR) {
+                Some(raw_speaker_id) => {
+                    let raw_speaker_id = raw_speaker_id.trim();
+                    let speaker_id = raw_speaker_id.to_lowercase();
+
+                    if !context.dialogue.actors.contains_key(speaker_id.as_str()) {
+                        report_undefined_actor(&speaker_id, raw_speaker_id, context);
+                    }
+
+                    speaker_id
                 }
+                None => speaker.to_string(),
+            };
 
-                speaker = speaker_id;
-            }
+            let text = parse_text(text, context);
 
-            return DialogueLine::SpeakerText { speaker, text };
+            return DialogueLine::SpeakerText {
+                speaker,
+                text,
+                annotations: HashMap::new(),
+                location: None,
+            };
         }
     }
 
     // Default to basic text line. Any failed parsing will be visible and thus obvious in testing.
-    DialogueLine::Text(line.to_string())
+    DialogueLine::Text {
+        text: parse_text(line, context),
+        annotations: HashMap::new(),
+        location: None,
+    }
+}
+
+/// Parses text content into styled spans of literal text and data references.
+///
+/// # Syntax
+/// - `{$variable_name}` - Substituted with the variable's current value
+/// - `{@actor_id.property}` - Substituted with the actor's property value
+/// - `**text**` - Bold text
+/// - `*text*` - Italic text
+/// - `<tag>text</tag>` or `<tag=value>text</tag>` - Custom tagged text, such as `<shake>`
+/// - `\character` - Escapes a character so it is kept as literal text, such as `\*`
+///
+/// Braces and angle brackets which do not contain a valid reference or tag are kept as literal
+/// text, as are bold and italic markers which don't touch the text they would style, such as the
+/// `*` in `Two * three is six`.
+///
+/// # Example
+/// ```text
+/// Welcome back, **{$player_name}**!
+/// {@oscar.name} is <color=red>*furious*</color>.
+/// ```
+///
+/// # Notes
+/// - Generates a warning if a referenced variable, actor or property hasn't been defined
+/// - Generates a warning if a reference, style or tag is never closed
+fn parse_text(from: &str, context: &mut ParseContext) -> DialogueText {
+    let mut text = DialogueText::default();
+    let mut style = TextStyle::default();
+    let mut literal = String::new();
+    let mut remaining = from;
+
+    // Where each open style started, for pointing diagnostics at unclosed markup
+    let mut bold_start = None;
+    let mut italic_start = None;
+    let mut tag_starts = Vec::new();
+
+    while let Some(character) = remaining.chars().next() {
+        // Check for escaped characters
+        // `\*`
+        if let Some(escaped) = remaining.strip_prefix(syntax::markup::ESCAPE)
+            && let Some(escaped_character) = escaped.chars().next()
+        {
+            literal.push(escaped_character);
+            remaining = &escaped[escaped_character.len_utf8()..];
+            continue;
+        }
+
+        // Check for bold and italic markers, checking bold first as it is the longer marker
+        // `**text**` or `*text*`
+        if let Some(after) = remaining.strip_prefix(syntax::markup::BOLD) {
+            if marker_touches_text(from, remaining, after, style.bold) {
+                push_literal_span(&mut text, &mut literal, &style);
+                style.bold = !style.bold;
+                bold_start = style.bold.then_some(remaining);
+            } else {
+                literal.push_str(syntax::markup::BOLD);
+            }
+
+            remaining = after;
+            continue;
+        }
+
+        if let Some(after) = remaining.strip_prefix(syntax::markup::ITALIC) {
+            if marker_touches_text(from, remaining, after, style.italic) {
+                push_literal_span(&mut text, &mut literal, &style);
+                style.italic = !style.italic;
+                italic_start = style.italic.then_some(remaining);
+            } else {
+                literal.push_str(syntax::markup::ITALIC);
+            }
+
+            remaining = after;
+            continue;
+        }
+
+        // Check for custom tags
+        // `<tag>`, `<tag=value>` or `</tag>`
+        if let Some((tag, is_closing, after)) = parse_markup_tag(remaining) {
+            push_literal_span(&mut text, &mut literal, &style);
+
+            let tag_text = &remaining[..remaining.len() - after.len()];
+
+            if !is_closing {
+                style.tags.push(tag);
+                tag_starts.push(tag_text);
+            } else if let Some(index) = style.tags.iter().rposition(|open| open.name == tag.name) {
+                style.tags.remove(index);
+                tag_starts.remove(index);
+            } else {
+                context.report_at(
+                    DiagnosticCode::UnmatchedClosingTag,
+                    format!("Closing tag without an open tag (</{}>)", tag.name),
+                    tag_text,
+                );
+            }
+
+            remaining = after;
+            continue;
+        }
+
+        // Check for data references
+        // `{$variable_name}` or `{@actor_id.property}`
+        if let Some(reference) = remaining.strip_prefix(syntax::references::START) {
+            match reference.split_once(syntax::references::END) {
+                Some((reference_text, after)) => {
+                    if let Some(segment) = parse_reference(reference_text.trim(), context) {
+                        push_literal_span(&mut text, &mut literal, &style);
+
+                        text.spans.push(TextSpan {
+                            content: segment,
+                            style: style.clone(),
+                        });
+
+                        remaining = after;
+                        continue;
+                    }
+                }
+
+                None if reference.starts_with(syntax::prefixes::VARIABLE)
+                    || reference.starts_with(syntax::prefixes::ACTOR) =>
+                {
+                    context
+                        .report_at(
+                            DiagnosticCode::UnclosedReference,
+                            format!(
+                                "Reference is never closed ({}{})",
+                                syntax::references::START,
+                                reference
+                            ),
+                            remaining,
+                        )
+                        .with_suggestion(format!(
+                            "Close the reference with `{}`",
+                            syntax::references::END
+                        ));
+                }
+
+                None => {}
+            }
+        }
+
+        literal.push(character);
+        remaining = &remaining[character.len_utf8()..];
+    }
+
+    push_literal_span(&mut text, &mut literal, &style);
+
+    if let Some(bold_start) = bold_start {
+        context.report_at(
+            DiagnosticCode::UnclosedMarkup,
+            format!("Bold markup is never closed ({})", syntax::markup::BOLD),
+            &bold_start[..syntax::markup::BOLD.len()],
+        );
+    }
+
+    if let Some(italic_start) = italic_start {
+        context.report_at(
+            DiagnosticCode::UnclosedMarkup,
+            format!("Italic markup is never closed ({})", syntax::markup::ITALIC),
+            &italic_start[..syntax::markup::ITALIC.len()],
+        );
+    }
+
+    for (tag, tag_start) in style.tags.iter().zip(tag_starts) {
+        context
+            .report_at(
+                DiagnosticCode::UnclosedMarkup,
+                format!("Tag is never closed (<{}>)", tag.name),
+                tag_start,
+            )
+            .with_suggestion(format!(
+                "Close the tag with `{}{}{}{}`",
+                syntax::markup::TAG_START,
+                syntax::markup::TAG_CLOSE,
+                tag.name,
+                syntax::markup::TAG_END
+            ));
+    }
+
+    text
+}
+
+/// Determines if a bold or italic marker touches the text it styles, as in Markdown, where
+/// opening markers must be followed by text and closing markers must follow it.
+///
+/// The marker starts at `marker` and is followed by `after`, both of which are within `from`.
+fn marker_touches_text(from: &str, marker: &str, after: &str, closing: bool) -> bool {
+    let touching = if closing {
+        from[..from.len() - marker.len()].chars().next_back()
+    } else {
+        after.chars().next()
+    };
+
+    touching.is_some_and(|character| !character.is_whitespace())
+}
+
+/// Pushes any pending literal text onto the given text as a span with the given style.
+fn push_literal_span(text: &mut DialogueText, literal: &mut String, style: &TextStyle) {
+    if literal.is_empty() {
+        return;
+    }
+
+    text.spans.push(TextSpan {
+        content: TextSegment::Literal(std::mem::take(literal)),
+        style: style.clone(),
+    });
+}
+
+/// Parses a custom markup tag at the start of the given text.
+///
+/// Returns the tag, whether it is a closing tag, and the text following it. Tag names may only
+/// contain letters, digits, `_` and `-`, so that uses such as `a < b` remain literal text.
+fn parse_markup_tag(from: &str) -> Option<(TextTag, bool, &str)> {
+    let (tag_text, after) = from
+        .strip_prefix(syntax::markup::TAG_START)?
+        .split_once(syntax::markup::TAG_END)?;
+
+    let (tag_text, is_closing) = match tag_text.strip_prefix(syntax::markup::TAG_CLOSE) {
+        Some(closing_tag_text) => (closing_tag_text, true),
+        None => (tag_text, false),
+    };
+
+    let (name, value) = match tag_text.split_once(syntax::delimiters::ASSIGNMENT) {
+        Some((name, value)) if !is_closing => (name, Some(value.trim().to_string())),
+        _ => (tag_text, None),
+    };
+
+    let is_valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+    if !is_valid_name {
+        return None;
+    }
+
+    let tag = TextTag {
+        name: name.to_lowercase(),
+        value,
+    };
+
+    Some((tag, is_closing, after))
+}
+
+/// Parses the contents of a data reference within text, without its braces.
+///
+/// Returns `None` if the contents are not a reference, in which case they should be kept as
+/// literal text.
+fn parse_reference(from: &str, context: &mut ParseContext) -> Option<TextSegment> {
+    if let Some(raw_variable_name) = from.strip_prefix(syntax::prefixes::VARIABLE) {
+        let raw_variable_name = raw_variable_name.trim();
+        let variable_name = raw_variable_name.to_lowercase();
+
+        if !context.has_variable(&variable_name) {
+            report_undefined_variable(&variable_name, raw_variable_name, context);
+        }
+
+        return Some(TextSegment::Variable(variable_name));
+    }
+
+    let actor_reference = from.strip_prefix(syntax::prefixes::ACTOR)?;
+
+    let Some((raw_actor_id, raw_property)) =
+        actor_reference.split_once(syntax::references::PROPERTY)
+    else {
+        context
+            .report_at(
+                DiagnosticCode::MissingActorProperty,
+                format!("Actor reference is missing a property ({from})"),
+                from,
+            )
+            .with_suggestion(format!(
+                "Reference a property of the actor: `{from}{}name`",
+                syntax::references::PROPERTY
+            ));
+        return None;
+    };
+
+    let actor_id = raw_actor_id.trim().to_lowercase();
+    let property = raw_property.trim().to_lowercase();
+
+    match context.dialogue.actors.get(&actor_id) {
+        // Every actor has a display name, even without an explicit name property
+        Some(actor) if property == "name" || actor.properties.contains_key(&property) => {}
+        Some(_) => {
+            context.report_at(
+                DiagnosticCode::UndefinedActorProperty,
+                format!("Actor property not found ({actor_id}.{property})"),
+                raw_property.trim(),
+            );
+        }
+        None => report_undefined_actor(&actor_id, raw_actor_id.trim(), context),
+    }
+
+    Some(TextSegment::ActorProperty {
+        actor: actor_id,
+        property,
+    })
 }
@@ -0,0 +1,194 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::collections::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// Level a kind of diagnostic is reported at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// Leave the diagnostic out entirely
+    Allow,
+    /// Report the diagnostic as a warning
+    Warn,
+    /// Report the diagnostic as an error, failing the build
+    Deny,
+}
+
+/// Level each kind of diagnostic is reported at, as configured by a project manifest or on the
+/// command line.
+///
+/// Kinds of diagnostic without a level configured are reported at their default severity.
+///
+/// # Example
+/// ```
+/// use dialogue_syntax::{DiagnosticCode, LintLevel, LintRegistry, Severity, parse};
+///
+/// let mut lints = LintRegistry::default();
+/// lints.set(DiagnosticCode::UndefinedVariable, LintLevel::Deny);
+///
+/// let mut result = parse("Hello {$name}".to_string());
+/// lints.apply(&mut result.diagnostics);
+/// assert_eq!(result.diagnostics[0].severity, Severity::Error);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LintRegistry {
+    levels: HashMap<DiagnosticCode, LintLevel>,
+}
+
+/// Error encountered when naming a lint which doesn't exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownLintError(pub String);
+
+/// Lints allowed on particular lines of a source by suppression comments.
+///
+/// Suppression comments allow the lints listed in them on the next line which isn't blank or
+/// another comment:
+/// ```text
+/// // lex-allow: unused-variable, silent-actor
+/// $unused: 10
+/// ```
+#[derive(Debug, Default)]
+pub struct LintSuppressions {
+    /// Lints allowed on each line, by line number
+    lines: HashMap<usize, HashSet<DiagnosticCode>>,
+}
+
+impl LintRegistry {
+    /// Sets the level diagnostics of the given kind are reported at.
+    pub fn set(&mut self, code: DiagnosticCode, level: LintLevel) {
+        self.levels.insert(code, level);
+    }
+
+    /// Gets the level diagnostics of the given kind are reported at, falling back to the level of
+    /// their default severity.
+    pub fn level(&self, code: DiagnosticCode) -> LintLevel {
+        self.levels
+            .get(&code)
+            .copied()
+            .unwrap_or(match code.default_severity() {
+                Severity::Error => LintLevel::Deny,
+                Severity::Warning | Severity::Info => LintLevel::Warn,
+            })
+    }
+
+    /// Applies the configured levels to the given diagnostics, removing those which are allowed
+    /// and changing the severity of the rest.
+    pub fn apply(&self, diagnostics: &mut Vec<Diagnostic>) {
+        diagnostics.retain_mut(|diagnostic| match self.levels.get(&diagnostic.code) {
+            Some(LintLevel::Allow) => false,
+            Some(LintLevel::Warn) => {
+                diagnostic.severity = Severity::Warning;
+                true
+            }
+            Some(LintLevel::Deny) => {
+                diagnostic.severity = Severity::Error;
+                true
+            }
+            None => true,
+        });
+    }
+}
+
+impl LintSuppressions {
+    /// Finds the suppression comments of a source, ignoring any lints in them which don't exist.
+    pub fn find(source: &str) -> Self {
+        let mut lines = HashMap::new();
+        let mut pending = HashSet::new();
+
+        for (line_index, line) in source.lines().enumerate() {
+            let line = line.trim();
+
+            if let Some(lints) = parse_lint_suppression(line) {
+                pending.extend(lints.filter_map(|lint| lint.parse::<DiagnosticCode>().ok()));
+                continue;
+            }
+
+            if line.is_empty() || is_plain_comment(line) {
+                continue;
+            }
+
+            if !pending.is_empty() {
+                lines.insert(line_index + 1, std::mem::take(&mut pending));
+            }
+        }
+
+        Self { lines }
+    }
+
+    /// Whether the diagnostic is allowed on the line it starts on.
+    pub fn allows(&self, diagnostic: &Diagnostic) -> bool {
+        self.lines
+            .get(&diagnostic.span.line)
+            .is_some_and(|lints| lints.contains(&diagnostic.code))
+    }
+
+    /// Removes the diagnostics which are allowed on the lines they start on.
+    pub fn apply(&self, diagnostics: &mut Vec<Diagnostic>) {
+        diagnostics.retain(|diagnostic| !self.allows(diagnostic));
+    }
+}
+
+/// Determines if a line is a comment, rather than a log step sharing its prefix.
+#[inline]
+fn is_plain_comment(line: &str) -> bool {
+    line.starts_with(syntax::comments::BASIC)
+        && ![
+            syntax::comments::INFO,
+            syntax::comments::WARNING,
+            syntax::comments::ERROR,
+        ]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+/// Parses the lints listed by a suppression comment, if the line is one.
+///
+/// # Syntax
+/// `// lex-allow: lint-name, other-lint-name`
+pub(crate) fn parse_lint_suppression(line: &str) -> Option<impl Iterator<Item = &str>> {
+    let lints = line
+        .strip_prefix(syntax::comments::BASIC)?
+        .trim_start()
+        .strip_prefix(syntax::comments::LINT_ALLOW)?
+        .trim_start()
+        .strip_prefix(syntax::delimiters::SEPARATOR)?;
+
+    Some(
+        lints
+            .split(',')
+            .map(str::trim)
+            .filter(|lint| !lint.is_empty()),
+    )
+}
+
+impl FromStr for DiagnosticCode {
+    type Err = UnknownLintError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        DiagnosticCode::ALL
+            .into_iter()
+            .find(|code| code.as_str() == name)
+            .ok_or_else(|| UnknownLintError(name.to_string()))
+    }
+}
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintLevel::Allow => write!(f, "allow"),
+            LintLevel::Warn => write!(f, "warn"),
+            LintLevel::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+impl fmt::Display for UnknownLintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown lint: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownLintError {}
@@ -0,0 +1,177 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::collections::*;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Project manifest, describing the sources of a dialogue project and how to build them.
+///
+/// Manifests are written in TOML and named `lex.toml`, with paths relative to the directory the
+/// manifest is in.
+///
+/// # Example
+/// ```toml
+/// [project]
+/// name = "my-game"
+/// sources = ["dialogues/**/*.lex"]
+/// entry = "intro"
+///
+/// [[outputs]]
+/// format = "json"
+/// path = "build/dialogue.json"
+///
+/// [[outputs]]
+/// format = "ron"
+/// path = "build/dialogue.ron"
+/// compiled = true
+///
+/// [locales]
+/// default = "en"
+/// available = ["en", "fr"]
+///
+/// [lints]
+/// undefined-variable = "deny"
+/// dangling-annotations = "allow"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest {
+    pub project: ProjectManifest,
+    pub outputs: Vec<OutputManifest>,
+    pub locales: LocaleManifest,
+    /// Level each kind of diagnostic is reported at, overriding its default severity
+    pub lints: LintRegistry,
+
+    /// Directory the manifest is in, which paths are relative to
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectManifest {
+    pub name: Option<String>,
+    /// Glob patterns matching the dialogue files of the project, defaulting to every `.lex` file
+    pub sources: Vec<String>,
+    /// Name of the section dialogue starts from, defaulting to the first section
+    pub entry: Option<String>,
+}
+
+/// A format to convert the project to, and where to write it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputManifest {
+    /// Output format (json, yaml, etc)
+    pub format: String,
+    /// Output file path, or stdout if none is given
+    pub path: Option<String>,
+    /// Whether to include source locations in the output
+    #[serde(default = "default_output_locations")]
+    pub locations: bool,
+    /// Whether to output the compiled dialogue, which never includes source locations
+    #[serde(default)]
+    pub compiled: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocaleManifest {
+    /// Locale the dialogue is written in
+    pub default: Option<String>,
+    /// Every locale the dialogue is available in, including the default
+    pub available: Vec<String>,
+}
+
+/// Error encountered while loading a manifest
+#[derive(Debug)]
+pub enum ManifestError {
+    Read(io::Error),
+    Parse(toml::de::Error),
+    InvalidSource(glob::PatternError),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Read(error) => write!(f, "Failed to read manifest: {error}"),
+            ManifestError::Parse(error) => write!(f, "Failed to parse manifest: {error}"),
+            ManifestError::InvalidSource(error) => {
+                write!(f, "Invalid source pattern in manifest: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+fn default_output_locations() -> bool {
+    true
+}
+
+impl Manifest {
+    /// Loads the manifest at the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Manifest, ManifestError> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path).map_err(ManifestError::Read)?;
+
+        let mut manifest: Manifest = toml::from_str(&raw).map_err(ManifestError::Parse)?;
+        manifest.root = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        Ok(manifest)
+    }
+
+    /// Finds the manifest of the project the given directory is in, by checking the directory
+    /// and then each of its parents.
+    pub fn find(from: impl AsRef<Path>) -> Option<PathBuf> {
+        from.as_ref()
+            .ancestors()
+            .map(|directory| directory.join(MANIFEST_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Gets the dialogue files matching the project's source patterns, in a stable order.
+    pub fn source_files(&self) -> Result<Vec<PathBuf>, ManifestError> {
+        let default_sources = [format!("**/*.{FILE_EXTENSION}")];
+        let sources = if self.project.sources.is_empty() {
+            &default_sources[..]
+        } else {
+            &self.project.sources[..]
+        };
+
+        let root = glob::Pattern::escape(&self.root.to_string_lossy());
+        let mut files = BTreeSet::new();
+
+        for source in sources {
+            let pattern = Path::new(&root).join(source);
+            let paths =
+                glob::glob(&pattern.to_string_lossy()).map_err(ManifestError::InvalidSource)?;
+
+            // Unreadable paths are skipped here, leaving the loader to report files it can't read
+            files.extend(paths.flatten().filter(|path| path.is_file()));
+        }
+
+        Ok(files.into_iter().collect())
+    }
+
+    /// Loads every dialogue file of the project into a single dialogue, with the lint levels of
+    /// the manifest applied to the diagnostics encountered.
+    pub fn load_project(&self) -> Result<ProjectResult, ManifestError> {
+        let mut loader = ProjectLoader::new();
+
+        for file in self.source_files()? {
+            loader.load(file);
+        }
+
+        let mut project = loader.finish();
+        self.apply_lints(&mut project.diagnostics);
+
+        Ok(project)
+    }
+
+    /// Applies the lint levels of the manifest to the given diagnostics, removing those which are
+    /// allowed and changing the severity of the rest.
+    pub fn apply_lints(&self, diagnostics: &mut Vec<Diagnostic>) {
+        self.lints.apply(diagnostics);
+    }
+}
@@ -1,9 +1,18 @@
 mod config;
+mod diagnostics;
 mod functions;
-mod syntax;
+mod lints;
+mod manifest;
+mod project;
+pub(crate) mod syntax;
+#[cfg(test)]
 mod tests;
 mod types;
 
 pub use config::*;
+pub use diagnostics::*;
 pub use functions::*;
+pub use lints::*;
+pub use manifest::*;
+pub use project::*;
 pub use types::*;
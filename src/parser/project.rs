@@ -0,0 +1,279 @@
+use super::*;
+use std::collections::*;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Reads the source of a file, given its path.
+pub type SourceReader = dyn FnMut(&Path) -> io::Result<String>;
+
+/// Result of loading a project, merging the dialogue of every file loaded along with the
+/// diagnostics encountered in each of them
+#[derive(Debug)]
+pub struct ProjectResult {
+    pub dialogue: Dialogue,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Source of each file loaded, by the path diagnostics refer to it with
+    pub sources: HashMap<String, String>,
+}
+
+impl ProjectResult {
+    /// Whether any error diagnostics were encountered in any file.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// Converts the result into an error if any error diagnostics were encountered.
+    pub fn into_result(self) -> Result<ProjectResult, ParseErrors> {
+        if self.has_errors() {
+            return Err(ParseErrors {
+                diagnostics: self.diagnostics,
+            });
+        }
+
+        Ok(self)
+    }
+
+    /// Renders a diagnostic for display in a terminal, using the source of the file it is in.
+    pub fn render_diagnostic(&self, diagnostic: &Diagnostic) -> String {
+        let path = diagnostic.file.as_deref().unwrap_or_default();
+        let source = self.sources.get(path).map(String::as_str);
+
+        diagnostic.render(source.unwrap_or_default(), path)
+    }
+}
+
+/// Loads dialogue spread across multiple files into a single dialogue.
+///
+/// Each file is parsed along with the files it includes, with include paths resolved relative to
+/// the including file. The definitions of an included file are available to the rest of the file
+/// including it, and its sections follow those of the file which first included it.
+///
+/// Each file is only loaded once, no matter how many times it is included, and files which
+/// include themselves through each other are reported as include cycles.
+///
+/// # Example
+/// ```no_run
+/// use dialogue_syntax::parser::ProjectLoader;
+///
+/// let mut loader = ProjectLoader::new();
+/// loader.load("dialogues/main.lex");
+///
+/// let project = loader.finish();
+/// assert!(!project.has_errors());
+/// ```
+pub struct ProjectLoader {
+    read: Box<SourceReader>,
+    /// Definitions of each file loaded, including those of the files it includes
+    loaded: HashMap<PathBuf, Dialogue>,
+    /// Files currently being loaded, from the first loaded to the most recently included
+    loading: Vec<PathBuf>,
+    /// Sections of each file, in the order the files were first included
+    sections: Vec<Vec<DialogueSection>>,
+    dialogue: Dialogue,
+    diagnostics: Vec<Diagnostic>,
+    sources: HashMap<String, String>,
+}
+
+impl Default for ProjectLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProjectLoader {
+    /// Creates a loader which reads files from disk.
+    pub fn new() -> Self {
+        Self::with_reader(|path| std::fs::read_to_string(path))
+    }
+
+    /// Creates a loader which reads files using the given reader, such as from memory or an
+    /// archive.
+    pub fn with_reader(read: impl FnMut(&Path) -> io::Result<String> + 'static) -> Self {
+        Self {
+            read: Box::new(read),
+            loaded: HashMap::new(),
+            loading: Vec::new(),
+            sections: Vec::new(),
+            dialogue: Dialogue::default(),
+            diagnostics: Vec::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Loads a file along with every file it includes.
+    ///
+    /// # Notes
+    /// - Generates an error if the file cannot be read
+    pub fn load(&mut self, path: impl AsRef<Path>) {
+        let path = normalize_path(path.as_ref());
+
+        if let Err(error) = self.load_file(&path) {
+            let mut diagnostic = Diagnostic::new(
+                DiagnosticCode::UnreadableFile,
+                format!("Failed to read file: {error}"),
+                SourceSpan::default(),
+            );
+            diagnostic.file = Some(path.display().to_string());
+
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Finishes loading, merging the sections of every file loaded into the dialogue.
+    pub fn finish(self) -> ProjectResult {
+        let mut dialogue = self.dialogue;
+        dialogue.sections = self.sections.into_iter().flatten().collect();
+
+        ProjectResult {
+            dialogue,
+            diagnostics: self.diagnostics,
+            sources: self.sources,
+        }
+    }
+
+    /// Loads a file unless it has been already, returning its definitions.
+    fn load_file(&mut self, path: &Path) -> io::Result<Dialogue> {
+        if let Some(definitions) = self.loaded.get(path) {
+            return Ok(definitions.clone());
+        }
+
+        let source = (self.read)(path)?;
+        let file = path.display().to_string();
+
+        // Reserve the file's place in the section order before its includes take theirs
+        let section_index = self.sections.len();
+        self.sections.push(Vec::new());
+
+        self.loading.push(path.to_path_buf());
+        let result = parse_with_includes(source.clone(), &file, &mut |include_path, context| {
+            self.include(path, include_path, context)
+        });
+        self.loading.pop();
+
+        let mut definitions = result.dialogue;
+        self.sections[section_index] = std::mem::take(&mut definitions.sections);
+
+        // Definitions replacing those from the same file were reported as it was parsed
+        let source_map = SourceMap::new(&source);
+        let mut redefinitions = find_redefinitions(&self.dialogue, &definitions)
+            .into_iter()
+            .filter(|redefinition| redefinition.location.file.as_ref() == Some(&file))
+            .map(|redefinition| {
+                let span = source_map.line_span(redefinition.location.line);
+                redefinition.to_diagnostic(span, Some(&file), &source_map)
+            })
+            .collect::<Vec<_>>();
+        LintSuppressions::find(&source).apply(&mut redefinitions);
+
+        self.dialogue.actors.extend(definitions.actors.clone());
+        self.dialogue
+            .variables
+            .extend(definitions.variables.clone());
+        self.dialogue
+            .functions
+            .extend(definitions.functions.clone());
+        self.dialogue
+            .variable_locations
+            .extend(definitions.variable_locations.clone());
+        self.diagnostics.extend(result.diagnostics);
+        self.diagnostics.extend(redefinitions);
+        self.sources.insert(file, source);
+        self.loaded.insert(path.to_path_buf(), definitions.clone());
+
+        Ok(definitions)
+    }
+
+    /// Handles an include directive found in the given file.
+    ///
+    /// # Notes
+    /// - Generates an error if the included file is already being loaded, forming a cycle
+    /// - Generates an error if the included file cannot be read
+    fn include(
+        &mut self,
+        from: &Path,
+        include_path: &str,
+        context: &mut ParseContext,
+    ) -> Option<Dialogue> {
+        let path = resolve_include_path(from, include_path);
+
+        if let Some(cycle_start) = self.loading.iter().position(|loading| *loading == path) {
+            let cycle = self.loading[cycle_start..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            context
+                .report_at(
+                    DiagnosticCode::IncludeCycle,
+                    format!("File includes itself [{cycle}]"),
+                    include_path,
+                )
+                .with_suggestion("Move the shared definitions into a file which both include");
+
+            return None;
+        }
+
+        match self.load_file(&path) {
+            Ok(definitions) => Some(definitions),
+            Err(error) => {
+                context.report_at(
+                    DiagnosticCode::UnreadableFile,
+                    format!("Failed to read included file [{}]: {error}", path.display()),
+                    include_path,
+                );
+
+                None
+            }
+        }
+    }
+}
+
+/// Loads a file along with every file it includes into a single dialogue.
+///
+/// See [`ProjectLoader`] for how files are merged.
+pub fn load_project(path: impl AsRef<Path>) -> ProjectResult {
+    let mut loader = ProjectLoader::new();
+    loader.load(path);
+    loader.finish()
+}
+
+/// Resolves the path of an included file relative to the file including it, adding the file
+/// extension if it is left out.
+fn resolve_include_path(from: &Path, include_path: &str) -> PathBuf {
+    let mut path = from.parent().unwrap_or(Path::new("")).join(include_path);
+
+    if path.extension().is_none() {
+        path.set_extension(FILE_EXTENSION);
+    }
+
+    normalize_path(&path)
+}
+
+/// Normalizes a path without touching the file system, so the same file is always referred to
+/// by the same path.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) {
+                    normalized.pop();
+                } else {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
@@ -19,6 +19,15 @@ pub mod prefixes {
     
     /// Response option prefix: `- response text`
     pub const RESPONSE: &str = "-";
+
+    /// Page extension prefix, keeping the line on the current page: `| text`
+    pub const PAGE_EXTENSION: &str = "|";
+}
+
+/// Directives which affect how dialogue sources are loaded
+pub mod directives {
+    /// Include directive, loading another file relative to the current one: `%include actors`
+    pub const INCLUDE: &str = "%include";
 }
 
 /// Comment and logging prefixes
@@ -34,6 +43,9 @@ pub mod comments {
     
     /// Error log prefix: `//! error message`
     pub const ERROR: &str = "//!";
+
+    /// Lint suppression keyword, allowing lints on the next line: `// lex-allow: unused-variable`
+    pub const LINT_ALLOW: &str = "lex-allow";
 }
 
 /// Navigation control prefixes
@@ -58,4 +70,124 @@ pub mod delimiters {
     
     /// Array closing bracket: `[item1, item2]`
     pub const ARRAY_END: &str = "]";
+
+    /// Expression group opening bracket: `($a + $b) * 2`
+    pub const GROUP_START: &str = "(";
+
+    /// Expression group closing bracket: `($a + $b) * 2`
+    pub const GROUP_END: &str = ")";
+
+    /// Quoted text within expressions: `"some text"`
+    pub const QUOTE: &str = "\"";
+}
+
+/// Data reference delimiters used within text: `{$variable}`, `{@actor.property}`
+pub mod references {
+    /// Reference opening brace: `{$variable}`
+    pub const START: &str = "{";
+
+    /// Reference closing brace: `{$variable}`
+    pub const END: &str = "}";
+
+    /// Actor property separator: `{@actor.property}`
+    pub const PROPERTY: &str = ".";
+}
+
+/// Rich text markup used within text
+pub mod markup {
+    /// Bold text marker: `**text**`
+    pub const BOLD: &str = "**";
+
+    /// Italic text marker: `*text*`
+    pub const ITALIC: &str = "*";
+
+    /// Tag opening bracket: `<tag>text</tag>`
+    pub const TAG_START: &str = "<";
+
+    /// Tag closing bracket: `<tag>text</tag>`
+    pub const TAG_END: &str = ">";
+
+    /// Closing tag prefix: `</tag>`
+    pub const TAG_CLOSE: &str = "/";
+
+    /// Escape prefix, keeping the next character as literal text: `\*`
+    pub const ESCAPE: &str = "\\";
+}
+
+/// Annotation delimiters and keys: `[key=value]`
+pub mod annotations {
+    /// Annotation opening bracket: `[key=value]`
+    pub const START: &str = "[";
+
+    /// Annotation closing bracket: `[key=value]`
+    pub const END: &str = "]";
+
+    /// Conditional block annotation key: `[if=$variable]`
+    pub const IF: &str = "if";
+}
+
+/// Block control markers and keywords
+pub mod blocks {
+    /// Block marker prefix, which closes the current block when alone: `~`
+    pub const MARKER: &str = "~";
+
+    /// Alternate branch keyword for conditional blocks: `~ ELSE`
+    pub const ELSE: &str = "ELSE";
+
+    /// Fixed count loop keyword: `~ REPEAT 3`
+    pub const REPEAT: &str = "REPEAT";
+
+    /// Conditional loop keyword: `~ WHILE $variable < 10`
+    pub const WHILE: &str = "WHILE";
+
+    /// Array loop keyword: `~ EACH $array as $item`
+    pub const EACH: &str = "EACH";
+
+    /// Array loop item binding keyword: `~ EACH $array as $item`
+    pub const EACH_BINDING: &str = "as";
+}
+
+/// Operators used in expressions, each separated from its operands by whitespace
+pub mod operators {
+    /// Addition, or concatenation when either side is text: `$gold + 10`
+    pub const ADD: &str = "+";
+
+    /// Subtraction, or negation before a single operand: `$gold - 10`
+    pub const SUBTRACT: &str = "-";
+
+    /// Multiplication: `$gold * 2`
+    pub const MULTIPLY: &str = "*";
+
+    /// Division: `$gold / 2`
+    pub const DIVIDE: &str = "/";
+
+    /// Remainder of division: `$gold % 2`
+    pub const REMAINDER: &str = "%";
+
+    /// Equality comparison: `$variable == value`
+    pub const EQUAL: &str = "==";
+
+    /// Inequality comparison: `$variable != value`
+    pub const NOT_EQUAL: &str = "!=";
+
+    /// Less than or equal comparison: `$variable <= value`
+    pub const LESS_OR_EQUAL: &str = "<=";
+
+    /// Greater than or equal comparison: `$variable >= value`
+    pub const GREATER_OR_EQUAL: &str = ">=";
+
+    /// Less than comparison: `$variable < value`
+    pub const LESS: &str = "<";
+
+    /// Greater than comparison: `$variable > value`
+    pub const GREATER: &str = ">";
+
+    /// Logical and: `$has_key and $has_map`
+    pub const AND: &str = "and";
+
+    /// Logical or: `$has_key or $has_lockpick`
+    pub const OR: &str = "or";
+
+    /// Logical not: `not $has_key`
+    pub const NOT: &str = "not";
 }
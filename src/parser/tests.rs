@@ -4,13 +4,25 @@ use crate::*;
 #[cfg(test)]
 use std::collections::HashMap;
 
-/// Helper function for tests - extracts dialogue from ParseResult and panics on warnings
+/// Helper function for tests - extracts dialogue without source locations from ParseResult and
+/// panics on diagnostics
 fn parse_test_helper(input: &str) -> super::types::Dialogue {
     let result = super::functions::parse(input.to_string());
-    if !result.warnings.is_empty() {
-        panic!("Unexpected warnings: {:?}", result.warnings);
+    if !result.diagnostics.is_empty() {
+        panic!("Unexpected diagnostics: {:?}", result.diagnostics);
     }
-    result.dialogue
+    let mut dialogue = result.dialogue;
+    dialogue.strip_source_locations();
+    dialogue
+}
+
+/// Helper function for tests - formats diagnostics as `Line N: message` for concise comparison
+fn diagnostic_messages(result: &super::functions::ParseResult) -> Vec<String> {
+    result
+        .diagnostics
+        .iter()
+        .map(|diagnostic| format!("Line {}: {}", diagnostic.span.line, diagnostic.message))
+        .collect()
 }
 
 #[test]
@@ -32,6 +44,7 @@ age: 26";
                     ),
                     ("age".to_string(), DialogueValue::Number(26.0)),
                 ]),
+                location: None,
             },
         )]),
         ..Default::default()
@@ -89,8 +102,10 @@ $foo = 2";
             name: META_SECTION_NAME.to_string(),
             steps: vec![DialogueStep::VariableAssign {
                 name: "foo".to_string(),
-                value: DialogueValue::Number(2.0),
+                value: DialogueExpression::Value(DialogueValue::Number(2.0)),
+                location: None,
             }],
+            location: None,
         }],
 
         ..Default::default()
@@ -117,6 +132,7 @@ fn test_function_definitions() {
                 DialogueFunction {
                     args: None,
                     result: None,
+                    location: None,
                 },
             ),
             (
@@ -124,6 +140,7 @@ fn test_function_definitions() {
                 DialogueFunction {
                     args: None,
                     result: Some(DialogueValue::Text("Default return value".to_string())),
+                    location: None,
                 },
             ),
             (
@@ -137,6 +154,7 @@ fn test_function_definitions() {
                         ("arg_2".to_string(), DialogueValue::Number(123.456)),
                     ])),
                     result: None,
+                    location: None,
                 },
             ),
             (
@@ -150,6 +168,7 @@ fn test_function_definitions() {
                         ("arg_2".to_string(), DialogueValue::Number(123.456)),
                     ])),
                     result: Some(DialogueValue::Text("Default return value".to_string())),
+                    location: None,
                 },
             ),
         ]),
@@ -161,6 +180,110 @@ fn test_function_definitions() {
     assert_eq!(result.functions, expected.functions);
 }
 
+#[test]
+fn test_function_calls() {
+    let input = r"
+$player_name: Nobody
+!save_game(slot=1)
+!get_player_name: Unknown
+
+=><= !save_game(slot=2)
+=><= !save_game()
+$player_name = !Get_Player_Name()";
+
+    let expected = vec![DialogueSection {
+        name: META_SECTION_NAME.to_string(),
+        steps: vec![
+            DialogueStep::FunctionCall {
+                name: "save_game".to_string(),
+                args: HashMap::from([("slot".to_string(), DialogueValue::Number(2.0))]),
+                result: None,
+                location: None,
+            },
+            DialogueStep::FunctionCall {
+                name: "save_game".to_string(),
+                args: HashMap::new(),
+                result: None,
+                location: None,
+            },
+            DialogueStep::FunctionCall {
+                name: "get_player_name".to_string(),
+                args: HashMap::new(),
+                result: Some("player_name".to_string()),
+                location: None,
+            },
+        ],
+        location: None,
+    }];
+
+    let result = parse_test_helper(input);
+
+    assert_eq!(result.sections, expected);
+}
+
+#[test]
+fn test_function_call_warnings() {
+    let input = r"
+$count: 0
+!save_game(slot=1)
+
+=><= !load_game()
+=><= !save_game(slot=first, name=Quick)
+$count = !save_game()";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec![
+            "Line 5: Function definition not found (load_game)".to_string(),
+            "Line 6: Function argument type does not match definition (save_game.slot)".to_string(),
+            "Line 6: Function argument not found (save_game.name)".to_string(),
+            "Line 7: Function has no result to store (save_game)".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_misplaced_function_definitions() {
+    let input = r"
+!save(slot=1)
+
+# intro
+!save(slot=2)
+[if=true]
+  !Save()
+~
+=> END";
+
+    let result = super::functions::parse(input.to_string());
+
+    // Definitions within sections are most likely meant as calls, so are left out
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec![
+            "Line 5: Functions must be defined before the first section (save)".to_string(),
+            "Line 7: Functions must be defined before the first section (save)".to_string(),
+        ]
+    );
+    assert!(result.has_errors());
+    assert_eq!(
+        result.diagnostics[0].suggestions,
+        vec![
+            "Call the function with `=><= !save()`, or move its definition before the first section"
+        ]
+    );
+
+    assert_eq!(
+        result.dialogue.functions["save"].args,
+        Some(HashMap::from([(
+            "slot".to_string(),
+            DialogueValue::Number(1.0)
+        )]))
+    );
+    assert_eq!(result.dialogue.sections[0].steps.len(), 2);
+}
+
 #[test]
 fn test_section_definitons() {
     let input = r"
@@ -173,15 +296,27 @@ Goodbye";
         sections: vec![
             DialogueSection {
                 name: "Intro".to_string(),
-                steps: vec![DialogueStep::Page(vec![DialogueLine::Text(
-                    "Hello".to_string(),
-                )])],
+                steps: vec![DialogueStep::Page {
+                    lines: vec![DialogueLine::Text {
+                        text: DialogueText::literal("Hello"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    }],
+                    location: None,
+                }],
+                location: None,
             },
             DialogueSection {
                 name: "Outro".to_string(),
-                steps: vec![DialogueStep::Page(vec![DialogueLine::Text(
-                    "Goodbye".to_string(),
-                )])],
+                steps: vec![DialogueStep::Page {
+                    lines: vec![DialogueLine::Text {
+                        text: DialogueText::literal("Goodbye"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    }],
+                    location: None,
+                }],
+                location: None,
             },
         ],
         ..Default::default()
@@ -204,11 +339,24 @@ fn test_comments_and_logs() {
         sections: vec![DialogueSection {
             name: META_SECTION_NAME.to_string(),
             steps: vec![
-                DialogueStep::Comment("Comment".to_string()),
-                DialogueStep::LogInfo("Info".to_string()),
-                DialogueStep::LogWarning("Warning".to_string()),
-                DialogueStep::LogError("Error".to_string()),
+                DialogueStep::Comment {
+                    text: "Comment".to_string(),
+                    location: None,
+                },
+                DialogueStep::LogInfo {
+                    text: "Info".to_string(),
+                    location: None,
+                },
+                DialogueStep::LogWarning {
+                    text: "Warning".to_string(),
+                    location: None,
+                },
+                DialogueStep::LogError {
+                    text: "Error".to_string(),
+                    location: None,
+                },
             ],
+            location: None,
         }],
         ..Default::default()
     };
@@ -236,20 +384,29 @@ Other Oscar: Hi";
                     "name".to_string(),
                     DialogueValue::Text("Oscar Robin".to_string()),
                 )]),
+                location: None,
             },
         )]),
         sections: vec![DialogueSection {
             name: META_SECTION_NAME.to_string(),
-            steps: vec![DialogueStep::Page(vec![
-                DialogueLine::SpeakerText {
-                    speaker: "oscar".to_string(),
-                    text: "Hello".to_string(),
-                },
-                DialogueLine::SpeakerText {
-                    speaker: "Other Oscar".to_string(),
-                    text: "Hi".to_string(),
-                },
-            ])],
+            steps: vec![DialogueStep::Page {
+                lines: vec![
+                    DialogueLine::SpeakerText {
+                        speaker: "oscar".to_string(),
+                        text: DialogueText::literal("Hello"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    },
+                    DialogueLine::SpeakerText {
+                        speaker: "Other Oscar".to_string(),
+                        text: DialogueText::literal("Hi"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    },
+                ],
+                location: None,
+            }],
+            location: None,
         }],
         ..Default::default()
     };
@@ -268,16 +425,117 @@ fn test_responses_and_nesting() {
     let expected = Dialogue {
         sections: vec![DialogueSection {
             name: META_SECTION_NAME.to_string(),
-            steps: vec![DialogueStep::Page(vec![
-                DialogueLine::Response {
-                    text: "Response 1".to_string(),
-                    pages: vec![],
+            steps: vec![DialogueStep::Page {
+                lines: vec![DialogueLine::Response {
+                    text: DialogueText::literal("Response 1"),
+                    pages: vec![DialogueStep::Page {
+                        lines: vec![DialogueLine::Response {
+                            text: DialogueText::literal("Nested Response"),
+                            pages: vec![],
+                            location: None,
+                        }],
+                        location: None,
+                    }],
+                    location: None,
+                }],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
+
+    let result = parse_test_helper(input);
+
+    assert_eq!(result.sections, expected.sections);
+}
+
+#[test]
+fn test_nested_response_steps() {
+    let input = r"
+Which way?
+- Left
+    You go left.
+
+    => END
+- Right
+    You go right.
+    - Run
+        You run.
+Both roads meet.
+
+Next page";
+
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![
+                DialogueStep::Page {
+                    lines: vec![
+                        DialogueLine::Text {
+                            text: DialogueText::literal("Which way?"),
+                            annotations: HashMap::new(),
+                            location: None,
+                        },
+                        DialogueLine::Response {
+                            text: DialogueText::literal("Left"),
+                            pages: vec![
+                                DialogueStep::Page {
+                                    lines: vec![DialogueLine::Text {
+                                        text: DialogueText::literal("You go left."),
+                                        annotations: HashMap::new(),
+                                        location: None,
+                                    }],
+                                    location: None,
+                                },
+                                DialogueStep::EndJump { location: None },
+                            ],
+                            location: None,
+                        },
+                        DialogueLine::Response {
+                            text: DialogueText::literal("Right"),
+                            pages: vec![DialogueStep::Page {
+                                lines: vec![
+                                    DialogueLine::Text {
+                                        text: DialogueText::literal("You go right."),
+                                        annotations: HashMap::new(),
+                                        location: None,
+                                    },
+                                    DialogueLine::Response {
+                                        text: DialogueText::literal("Run"),
+                                        pages: vec![DialogueStep::Page {
+                                            lines: vec![DialogueLine::Text {
+                                                text: DialogueText::literal("You run."),
+                                                annotations: HashMap::new(),
+                                                location: None,
+                                            }],
+                                            location: None,
+                                        }],
+                                        location: None,
+                                    },
+                                ],
+                                location: None,
+                            }],
+                            location: None,
+                        },
+                        DialogueLine::Text {
+                            text: DialogueText::literal("Both roads meet."),
+                            annotations: HashMap::new(),
+                            location: None,
+                        },
+                    ],
+                    location: None,
                 },
-                DialogueLine::Response {
-                    text: "Nested Response".to_string(),
-                    pages: vec![],
+                DialogueStep::Page {
+                    lines: vec![DialogueLine::Text {
+                        text: DialogueText::literal("Next page"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    }],
+                    location: None,
                 },
-            ])],
+            ],
+            location: None,
         }],
         ..Default::default()
     };
@@ -298,24 +556,91 @@ fn test_manual_page_extensions() {
     let expected = Dialogue {
         sections: vec![DialogueSection {
             name: META_SECTION_NAME.to_string(),
-            steps: vec![DialogueStep::Page(vec![
-                DialogueLine::Text("This is a single page".to_string()),
-                DialogueLine::Response {
-                    text: "Wow!".to_string(),
-                    pages: vec![],
+            steps: vec![DialogueStep::Page {
+                lines: vec![
+                    DialogueLine::Text {
+                        text: DialogueText::literal("This is a single page"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    },
+                    DialogueLine::Response {
+                        text: DialogueText::literal("Wow!"),
+                        pages: vec![],
+                        location: None,
+                    },
+                    DialogueLine::Response {
+                        text: DialogueText::literal("More!"),
+                        pages: vec![],
+                        location: None,
+                    },
+                ],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
+
+    let result = parse_test_helper(input);
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_page_extensions_keep_new_steps_on_page() {
+    let input = r"
+@Oscar
+name: Oscar Robin
+
+@Oscar: Hello
+| @Oscar: Still here
+| => not a jump
+
+Next page";
+
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![
+                DialogueStep::Page {
+                    lines: vec![
+                        DialogueLine::SpeakerText {
+                            speaker: "oscar".to_string(),
+                            text: DialogueText::literal("Hello"),
+                            annotations: HashMap::new(),
+                            location: None,
+                        },
+                        DialogueLine::SpeakerText {
+                            speaker: "oscar".to_string(),
+                            text: DialogueText::literal("Still here"),
+                            annotations: HashMap::new(),
+                            location: None,
+                        },
+                        DialogueLine::Text {
+                            text: DialogueText::literal("=> not a jump"),
+                            annotations: HashMap::new(),
+                            location: None,
+                        },
+                    ],
+                    location: None,
                 },
-                DialogueLine::Response {
-                    text: "More!".to_string(),
-                    pages: vec![],
+                DialogueStep::Page {
+                    lines: vec![DialogueLine::Text {
+                        text: DialogueText::literal("Next page"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    }],
+                    location: None,
                 },
-            ])],
+            ],
+            location: None,
         }],
         ..Default::default()
     };
 
     let result = parse_test_helper(input);
 
-    assert_eq!(result, expected);
+    assert_eq!(result.sections, expected.sections);
 }
 
 #[test]
@@ -324,32 +649,231 @@ fn test_annotated_dialogue() {
 [mood=info]
 This is annotated.";
 
-    let expected = Dialogue::default();
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![DialogueStep::Page {
+                lines: vec![DialogueLine::Text {
+                    text: DialogueText::literal("This is annotated."),
+                    annotations: HashMap::from([(
+                        "mood".to_string(),
+                        DialogueValue::Text("info".to_string()),
+                    )]),
+                    location: None,
+                }],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
 
     let result = parse_test_helper(input);
 
     assert_eq!(result, expected);
 }
 
+#[test]
+fn test_annotations_within_page() {
+    let input = r"
+Hello
+[mood=sad]
+Goodbye";
+
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![DialogueStep::Page {
+                lines: vec![
+                    DialogueLine::Text {
+                        text: DialogueText::literal("Hello"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    },
+                    DialogueLine::Text {
+                        text: DialogueText::literal("Goodbye"),
+                        annotations: HashMap::from([(
+                            "mood".to_string(),
+                            DialogueValue::Text("sad".to_string()),
+                        )]),
+                        location: None,
+                    },
+                ],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
+
+    let result = parse_test_helper(input);
+
+    assert_eq!(result.sections, expected.sections);
+}
+
+#[test]
+fn test_annotations_follow_their_line() {
+    let input = r"
+@Oscar
+name: Oscar Robin
+
+@Oscar: Hello
+[mood=happy, volume=0.5]
+[tags=[wave, smile]]
+@Oscar: Welcome!";
+
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![
+                DialogueStep::Page {
+                    lines: vec![DialogueLine::SpeakerText {
+                        speaker: "oscar".to_string(),
+                        text: DialogueText::literal("Hello"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    }],
+                    location: None,
+                },
+                DialogueStep::Page {
+                    lines: vec![DialogueLine::SpeakerText {
+                        speaker: "oscar".to_string(),
+                        text: DialogueText::literal("Welcome!"),
+                        annotations: HashMap::from([
+                            ("mood".to_string(), DialogueValue::Text("happy".to_string())),
+                            ("volume".to_string(), DialogueValue::Number(0.5)),
+                            (
+                                "tags".to_string(),
+                                DialogueValue::Array(vec!["wave".to_string(), "smile".to_string()]),
+                            ),
+                        ]),
+                        location: None,
+                    }],
+                    location: None,
+                },
+            ],
+            location: None,
+        }],
+        ..Default::default()
+    };
+
+    let result = parse_test_helper(input);
+
+    assert_eq!(result.sections, expected.sections);
+}
+
 #[test]
 fn test_conditional_blocks() {
     let input = r"
+$var: true
+
 [if=$var]
 Text
 ~ ELSE
 Other
 ~";
 
-    let expected = Dialogue::default();
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![DialogueStep::Conditional {
+                condition: DialogueExpression::Variable("var".to_string()),
+                then_steps: vec![DialogueStep::Page {
+                    lines: vec![DialogueLine::Text {
+                        text: DialogueText::literal("Text"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    }],
+                    location: None,
+                }],
+                else_steps: vec![DialogueStep::Page {
+                    lines: vec![DialogueLine::Text {
+                        text: DialogueText::literal("Other"),
+                        annotations: HashMap::new(),
+                        location: None,
+                    }],
+                    location: None,
+                }],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
 
     let result = parse_test_helper(input);
 
-    assert_eq!(result, expected);
+    assert_eq!(result.sections, expected.sections);
+}
+
+#[test]
+fn test_nested_conditional_blocks() {
+    let input = r"
+$outer: true
+$inner: false
+
+[if=$outer]
+[if=$inner]
+Inner
+~
+=> END
+~";
+
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![DialogueStep::Conditional {
+                condition: DialogueExpression::Variable("outer".to_string()),
+                then_steps: vec![
+                    DialogueStep::Conditional {
+                        condition: DialogueExpression::Variable("inner".to_string()),
+                        then_steps: vec![DialogueStep::Page {
+                            lines: vec![DialogueLine::Text {
+                                text: DialogueText::literal("Inner"),
+                                annotations: HashMap::new(),
+                                location: None,
+                            }],
+                            location: None,
+                        }],
+                        else_steps: vec![],
+                        location: None,
+                    },
+                    DialogueStep::EndJump { location: None },
+                ],
+                else_steps: vec![],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
+
+    let result = parse_test_helper(input);
+
+    assert_eq!(result.sections, expected.sections);
+}
+
+#[test]
+fn test_unterminated_conditional_block() {
+    let input = r"
+[if=true]
+Text
+# Next";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec!["Line 2: Conditional block is never closed".to_string()]
+    );
 }
 
 #[test]
 fn test_branching_repeat_while_each() {
     let input = r"
+$var: 0
+$arr: [first, second]
+
 ~ REPEAT 3
 Repeat
 ~
@@ -358,13 +882,382 @@ While
 ~
 ~ EACH $arr as $item
 Each
+$item = done
 ~";
 
-    let expected = Dialogue::default();
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![
+                DialogueStep::Repeat {
+                    count: 3,
+                    steps: vec![DialogueStep::Page {
+                        lines: vec![DialogueLine::Text {
+                            text: DialogueText::literal("Repeat"),
+                            annotations: HashMap::new(),
+                            location: None,
+                        }],
+                        location: None,
+                    }],
+                    location: None,
+                },
+                DialogueStep::While {
+                    condition: DialogueExpression::Binary {
+                        left: Box::new(DialogueExpression::Variable("var".to_string())),
+                        operator: BinaryOperator::Less,
+                        right: Box::new(DialogueExpression::Value(DialogueValue::Number(10.0))),
+                    },
+                    steps: vec![DialogueStep::Page {
+                        lines: vec![DialogueLine::Text {
+                            text: DialogueText::literal("While"),
+                            annotations: HashMap::new(),
+                            location: None,
+                        }],
+                        location: None,
+                    }],
+                    location: None,
+                },
+                DialogueStep::Each {
+                    array: "arr".to_string(),
+                    item: "item".to_string(),
+                    steps: vec![
+                        DialogueStep::Page {
+                            lines: vec![DialogueLine::Text {
+                                text: DialogueText::literal("Each"),
+                                annotations: HashMap::new(),
+                                location: None,
+                            }],
+                            location: None,
+                        },
+                        DialogueStep::VariableAssign {
+                            name: "item".to_string(),
+                            value: DialogueExpression::Value(DialogueValue::Text(
+                                "done".to_string(),
+                            )),
+                            location: None,
+                        },
+                    ],
+                    location: None,
+                },
+            ],
+            location: None,
+        }],
+        ..Default::default()
+    };
 
     let result = parse_test_helper(input);
 
-    assert_eq!(result, expected);
+    assert_eq!(result.sections, expected.sections);
+}
+
+#[test]
+fn test_loop_warnings() {
+    let input = r"
+$number: 1
+
+~ REPEAT many
+~
+~ EACH $number as $item
+~
+$item = outside
+~ EACH $nope as
+~
+~ EACH
+~
+~ EACH names as $name
+~
+~ EACH $names as $first as $second
+~";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec![
+            "Line 4: Invalid repeat count (many)".to_string(),
+            "Line 6: Loop variable is not an array [number]".to_string(),
+            "Line 8: Static variable definition not found [item]".to_string(),
+            "Line 9: Loop is missing an item".to_string(),
+            "Line 11: Loop is missing an array and item".to_string(),
+            "Line 13: Expected a variable in loop (names)".to_string(),
+            "Line 15: Expected a variable in loop ($first as $second)".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_expressions() {
+    let input = r#"
+@Oscar
+age: 26
+$gold: 0
+$name: Nobody
+$ok: false
+
+$gold = $gold + 10 * 2
+$ok = $gold >= 10 and not ($name == "Nobody" or @oscar.age < 18)
+$name = "Salt and " + Pepper
+$name = Salt and Pepper"#;
+
+    let variable = |name: &str| Box::new(DialogueExpression::Variable(name.to_string()));
+    let value = |value: DialogueValue| Box::new(DialogueExpression::Value(value));
+    let binary = |left, operator, right| {
+        Box::new(DialogueExpression::Binary {
+            left,
+            operator,
+            right,
+        })
+    };
+
+    let expected = vec![
+        DialogueStep::VariableAssign {
+            name: "gold".to_string(),
+            value: *binary(
+                variable("gold"),
+                BinaryOperator::Add,
+                binary(
+                    value(DialogueValue::Number(10.0)),
+                    BinaryOperator::Multiply,
+                    value(DialogueValue::Number(2.0)),
+                ),
+            ),
+            location: None,
+        },
+        DialogueStep::VariableAssign {
+            name: "ok".to_string(),
+            value: *binary(
+                binary(
+                    variable("gold"),
+                    BinaryOperator::GreaterOrEqual,
+                    value(DialogueValue::Number(10.0)),
+                ),
+                BinaryOperator::And,
+                Box::new(DialogueExpression::Unary {
+                    operator: UnaryOperator::Not,
+                    operand: binary(
+                        binary(
+                            variable("name"),
+                            BinaryOperator::Equal,
+                            value(DialogueValue::Text("Nobody".to_string())),
+                        ),
+                        BinaryOperator::Or,
+                        binary(
+                            Box::new(DialogueExpression::ActorProperty {
+                                actor: "oscar".to_string(),
+                                property: "age".to_string(),
+                            }),
+                            BinaryOperator::Less,
+                            value(DialogueValue::Number(18.0)),
+                        ),
+                    ),
+                }),
+            ),
+            location: None,
+        },
+        DialogueStep::VariableAssign {
+            name: "name".to_string(),
+            value: *binary(
+                value(DialogueValue::Text("Salt and ".to_string())),
+                BinaryOperator::Add,
+                value(DialogueValue::Text("Pepper".to_string())),
+            ),
+            location: None,
+        },
+        DialogueStep::VariableAssign {
+            name: "name".to_string(),
+            value: DialogueExpression::Value(DialogueValue::Text("Salt and Pepper".to_string())),
+            location: None,
+        },
+    ];
+
+    let result = parse_test_helper(input);
+
+    assert_eq!(result.sections[0].steps, expected);
+}
+
+#[test]
+fn test_invalid_expressions() {
+    let input = r"
+$gold: 0
+
+$gold = $gold +
+$gold = ($gold * 2";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec![
+            "Line 4: Invalid expression ($gold +)".to_string(),
+            "Line 5: Invalid expression (($gold * 2)".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_diagnostic_spans() {
+    let input = "$gold: 0\n~ REPEAT 2\n  $Gld = 10\n~ ELSE";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        result.diagnostics,
+        vec![
+            Diagnostic {
+                severity: Severity::Warning,
+                code: DiagnosticCode::UndefinedVariable,
+                message: "Static variable definition not found [gld]".to_string(),
+                file: None,
+                span: SourceSpan {
+                    start: 23,
+                    end: 26,
+                    line: 3,
+                    column: 4,
+                },
+                related: Vec::new(),
+                suggestions: vec!["Define the variable first: `$gld: value`".to_string()],
+            },
+            Diagnostic {
+                severity: Severity::Error,
+                code: DiagnosticCode::LoopElse,
+                message: "Loop blocks cannot have an else branch".to_string(),
+                file: None,
+                span: SourceSpan {
+                    start: 32,
+                    end: 38,
+                    line: 4,
+                    column: 1,
+                },
+                related: vec![RelatedSpan {
+                    file: None,
+                    span: SourceSpan {
+                        start: 9,
+                        end: 19,
+                        line: 2,
+                        column: 1,
+                    },
+                    message: "loop block starts here".to_string(),
+                }],
+                suggestions: Vec::new(),
+            },
+            Diagnostic {
+                severity: Severity::Error,
+                code: DiagnosticCode::UnclosedBlock,
+                message: "Loop block is never closed".to_string(),
+                file: None,
+                span: SourceSpan {
+                    start: 9,
+                    end: 19,
+                    line: 2,
+                    column: 1,
+                },
+                related: Vec::new(),
+                suggestions: vec!["Close the block with `~`".to_string()],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_fatal_parse_errors() {
+    let input = r"
+$gold:
+!save(slot=1
+
+#
+=><= !save(slot=2
+Hello {$gold}";
+
+    let result = super::functions::try_parse(input.to_string());
+
+    let Err(parse_errors) = result else {
+        panic!("Expected parse errors");
+    };
+
+    assert_eq!(
+        parse_errors
+            .errors()
+            .map(|diagnostic| diagnostic.code)
+            .collect::<Vec<_>>(),
+        vec![
+            DiagnosticCode::MissingValue,
+            DiagnosticCode::UnclosedArguments,
+            DiagnosticCode::EmptySectionName,
+            DiagnosticCode::UnclosedArguments,
+        ]
+    );
+
+    // Parsing recovers, so later references to the broken definitions don't cascade
+    assert_eq!(parse_errors.diagnostics.len(), 4);
+
+    let recovered = super::functions::parse(input.to_string()).dialogue;
+
+    assert_eq!(
+        recovered.functions["save"].args,
+        Some(HashMap::from([(
+            "slot".to_string(),
+            DialogueValue::Number(1.0)
+        )]))
+    );
+}
+
+#[test]
+fn test_parse_warnings_are_not_fatal() {
+    let input = r"
+Hello {$gold}";
+
+    let result = super::functions::try_parse(input.to_string());
+
+    assert!(result.is_ok_and(|result| result.diagnostics.len() == 1));
+}
+
+#[test]
+fn test_source_locations() {
+    let input =
+        "@Oscar\n\n$gold: 10\n!give\n\n# Intro\n[if=true]\n  Hello\n  - Leave\n    => END\n~";
+
+    let result = super::functions::parse_file(input.to_string(), "intro.lex");
+    let location = |line, column| {
+        Some(SourceLocation {
+            file: Some("intro.lex".to_string()),
+            line,
+            column,
+        })
+    };
+
+    let section = &result.dialogue.sections[0];
+    assert_eq!(section.location, location(6, 1));
+
+    let DialogueStep::Conditional { then_steps, .. } = &section.steps[0] else {
+        panic!("Expected a conditional step");
+    };
+    assert_eq!(section.steps[0].location(), location(7, 1).as_ref());
+    assert_eq!(then_steps[0].location(), location(8, 3).as_ref());
+
+    let DialogueStep::Page { lines, .. } = &then_steps[0] else {
+        panic!("Expected a page step");
+    };
+    assert_eq!(lines[1].location(), location(9, 3).as_ref());
+
+    let DialogueLine::Response { pages, .. } = &lines[1] else {
+        panic!("Expected a response line");
+    };
+    assert_eq!(pages[0].location(), location(10, 5).as_ref());
+
+    let dialogue = &result.dialogue;
+    assert_eq!(dialogue.actors["oscar"].location, location(1, 1));
+    assert_eq!(
+        dialogue.variable_locations.get("gold").cloned(),
+        location(3, 1)
+    );
+    assert_eq!(dialogue.functions["give"].location, location(4, 1));
+
+    // Stripped locations are left out of exports entirely
+    let mut dialogue = result.dialogue;
+    dialogue.strip_source_locations();
+
+    let exported = serde_json::to_string(&dialogue).unwrap();
+    assert!(!exported.contains("location"));
 }
 
 #[test]
@@ -379,11 +1272,18 @@ fn test_jumps_and_bounces() {
         sections: vec![DialogueSection {
             name: META_SECTION_NAME.to_string(),
             steps: vec![
-                DialogueStep::SectionJump("#Outro".to_string()),
-                DialogueStep::SectionBounce("#Outro".to_string()),
-                DialogueStep::EndJump,
-                DialogueStep::TerminateJump,
+                DialogueStep::SectionJump {
+                    target: "#Outro".to_string(),
+                    location: None,
+                },
+                DialogueStep::SectionBounce {
+                    target: "#Outro".to_string(),
+                    location: None,
+                },
+                DialogueStep::EndJump { location: None },
+                DialogueStep::TerminateJump { location: None },
             ],
+            location: None,
         }],
         ..Default::default()
     };
@@ -395,22 +1295,592 @@ fn test_jumps_and_bounces() {
 
 #[test]
 fn test_data_references() {
-    let input = "This is a variable: {$var}. This is a property: {@actor.prop}.";
+    let input = r"
+$var: 1
 
-    let expected = Dialogue::default();
+@actor
+prop: value
+
+@actor: This is a variable: {$var}. This is a property: {@actor.prop}.
+Braces without references {stay} as text.";
+
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![DialogueStep::Page {
+                lines: vec![
+                    DialogueLine::SpeakerText {
+                        speaker: "actor".to_string(),
+                        text: DialogueText {
+                            spans: vec![
+                                TextSpan::plain(TextSegment::Literal(
+                                    "This is a variable: ".to_string(),
+                                )),
+                                TextSpan::plain(TextSegment::Variable("var".to_string())),
+                                TextSpan::plain(TextSegment::Literal(
+                                    ". This is a property: ".to_string(),
+                                )),
+                                TextSpan::plain(TextSegment::ActorProperty {
+                                    actor: "actor".to_string(),
+                                    property: "prop".to_string(),
+                                }),
+                                TextSpan::plain(TextSegment::Literal(".".to_string())),
+                            ],
+                        },
+                        annotations: HashMap::new(),
+                        location: None,
+                    },
+                    DialogueLine::Text {
+                        text: DialogueText::literal("Braces without references {stay} as text."),
+                        annotations: HashMap::new(),
+                        location: None,
+                    },
+                ],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
 
     let result = parse_test_helper(input);
 
-    assert_eq!(result, expected);
+    assert_eq!(result.sections, expected.sections);
+}
+
+#[test]
+fn test_undefined_data_references() {
+    let input = r"
+@actor
+prop: value
+
+{$missing} {@actor.missing} {@ghost.name} {@actor}";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec![
+            "Line 5: Static variable definition not found [missing]".to_string(),
+            "Line 5: Actor property not found (actor.missing)".to_string(),
+            "Line 5: Actor definition not found (ghost)".to_string(),
+            "Line 5: Actor reference is missing a property (@actor)".to_string(),
+        ]
+    );
 }
 
 #[test]
 fn test_rich_text() {
     let input = "This is **bold** and *italic*";
 
-    let expected = Dialogue::default();
+    let bold = TextStyle {
+        bold: true,
+        ..Default::default()
+    };
+
+    let italic = TextStyle {
+        italic: true,
+        ..Default::default()
+    };
+
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![DialogueStep::Page {
+                lines: vec![DialogueLine::Text {
+                    text: DialogueText {
+                        spans: vec![
+                            TextSpan::plain(TextSegment::Literal("This is ".to_string())),
+                            TextSpan {
+                                content: TextSegment::Literal("bold".to_string()),
+                                style: bold,
+                            },
+                            TextSpan::plain(TextSegment::Literal(" and ".to_string())),
+                            TextSpan {
+                                content: TextSegment::Literal("italic".to_string()),
+                                style: italic,
+                            },
+                        ],
+                    },
+                    annotations: HashMap::new(),
+                    location: None,
+                }],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
 
     let result = parse_test_helper(input);
 
     assert_eq!(result, expected);
 }
+
+#[test]
+fn test_rich_text_tags() {
+    let input = r"
+$name: Hero
+
+<shake>**{$name}**</shake> <color=red>sees</color> 1 \* 2 < 3";
+
+    let shake = TextTag {
+        name: "shake".to_string(),
+        value: None,
+    };
+
+    let expected = Dialogue {
+        sections: vec![DialogueSection {
+            name: META_SECTION_NAME.to_string(),
+            steps: vec![DialogueStep::Page {
+                lines: vec![DialogueLine::Text {
+                    text: DialogueText {
+                        spans: vec![
+                            TextSpan {
+                                content: TextSegment::Variable("name".to_string()),
+                                style: TextStyle {
+                                    bold: true,
+                                    tags: vec![shake],
+                                    ..Default::default()
+                                },
+                            },
+                            TextSpan::plain(TextSegment::Literal(" ".to_string())),
+                            TextSpan {
+                                content: TextSegment::Literal("sees".to_string()),
+                                style: TextStyle {
+                                    tags: vec![TextTag {
+                                        name: "color".to_string(),
+                                        value: Some("red".to_string()),
+                                    }],
+                                    ..Default::default()
+                                },
+                            },
+                            TextSpan::plain(TextSegment::Literal(" 1 * 2 < 3".to_string())),
+                        ],
+                    },
+                    annotations: HashMap::new(),
+                    location: None,
+                }],
+                location: None,
+            }],
+            location: None,
+        }],
+        ..Default::default()
+    };
+
+    let result = parse_test_helper(input);
+
+    assert_eq!(result.sections, expected.sections);
+}
+
+#[test]
+fn test_unclosed_rich_text() {
+    let input = "**Bold <shake>shaking </color>";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec![
+            "Line 1: Closing tag without an open tag (</color>)".to_string(),
+            "Line 1: Bold markup is never closed (**)".to_string(),
+            "Line 1: Tag is never closed (<shake>)".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_bare_markup_markers() {
+    let input = "Two * three is six, ** is not bold and *this * is* italic";
+
+    let result = super::functions::parse(input.to_string());
+    assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+
+    let DialogueStep::Page { lines, .. } = &result.dialogue.sections[0].steps[0] else {
+        panic!("Expected a page");
+    };
+    let DialogueLine::Text { text, .. } = &lines[0] else {
+        panic!("Expected a text line");
+    };
+
+    // Markers surrounded by whitespace are kept as literal text, even within styled text
+    assert_eq!(
+        text.spans,
+        vec![
+            TextSpan::plain(TextSegment::Literal(
+                "Two * three is six, ** is not bold and ".to_string()
+            )),
+            TextSpan {
+                content: TextSegment::Literal("this * is".to_string()),
+                style: TextStyle {
+                    italic: true,
+                    ..Default::default()
+                },
+            },
+            TextSpan::plain(TextSegment::Literal(" italic".to_string())),
+        ]
+    );
+}
+
+/// Helper function for tests - loads a project from in-memory files
+fn load_test_project(entry: &str, files: &[(&str, &str)]) -> super::project::ProjectResult {
+    let files: HashMap<std::path::PathBuf, String> = files
+        .iter()
+        .map(|(path, source)| (path.into(), source.to_string()))
+        .collect();
+
+    let mut loader = ProjectLoader::with_reader(move |path| {
+        files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+    });
+    loader.load(entry);
+    loader.finish()
+}
+
+#[test]
+fn test_project_includes() {
+    let files = [
+        (
+            "story/main.lex",
+            "%include common/actors\n%include \"chapter.lex\"\n# start\noscar: Hello {$gold}\n=> chapter",
+        ),
+        (
+            "story/chapter.lex",
+            "%include ./common/../common/actors.lex\n# chapter\noscar: Welcome back",
+        ),
+        ("story/common/actors.lex", "@oscar\nname: Oscar\n$gold: 10"),
+    ];
+
+    let project = load_test_project("story/main.lex", &files);
+
+    assert!(project.diagnostics.is_empty(), "{:?}", project.diagnostics);
+
+    // Sections of included files follow those of the file including them
+    let section_names: Vec<_> = project
+        .dialogue
+        .sections
+        .iter()
+        .map(|section| section.name.as_str())
+        .collect();
+    assert_eq!(section_names, vec!["start", "chapter"]);
+
+    assert!(project.dialogue.actors.contains_key("oscar"));
+    assert!(project.dialogue.variables.contains_key("gold"));
+
+    let chapter = &project.dialogue.sections[1];
+    assert_eq!(
+        chapter
+            .location
+            .as_ref()
+            .and_then(|location| location.file.as_deref()),
+        Some("story/chapter.lex")
+    );
+
+    // Includes directly after a line of text end its page, rather than joining it
+    let files = [
+        ("intro.lex", "# intro\nHello\n%include common"),
+        ("common.lex", "$gold: 10"),
+    ];
+    let project = load_test_project("intro.lex", &files);
+
+    assert!(project.diagnostics.is_empty(), "{:?}", project.diagnostics);
+    assert!(project.dialogue.variables.contains_key("gold"));
+
+    let DialogueStep::Page { lines, .. } = &project.dialogue.sections[0].steps[0] else {
+        panic!("Expected a page");
+    };
+    assert_eq!(lines.len(), 1);
+}
+
+#[test]
+fn test_project_include_errors() {
+    let files = [
+        (
+            "a.lex",
+            "%include b\n%include missing\n%include\n# a\nHello",
+        ),
+        ("b.lex", "%include a.lex\n# b\nHello {$gold}"),
+    ];
+
+    let project = load_test_project("a.lex", &files);
+
+    let diagnostics: Vec<_> = project
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.file.as_deref().unwrap_or_default(),
+                diagnostic.span.line,
+                diagnostic.code,
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            ("b.lex", 1, DiagnosticCode::IncludeCycle),
+            ("b.lex", 3, DiagnosticCode::UndefinedVariable),
+            ("a.lex", 2, DiagnosticCode::UnreadableFile),
+            ("a.lex", 3, DiagnosticCode::MissingValue),
+        ]
+    );
+    assert_eq!(
+        project.diagnostics[0].message,
+        "File includes itself [a.lex -> b.lex -> a.lex]"
+    );
+    assert!(project.has_errors());
+
+    // Without a project loader, includes are left unresolved
+    let result = super::functions::parse("%include b\n# a\nHello".to_string());
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec!["Line 1: Include cannot be resolved outside of a project [b]"]
+    );
+}
+
+#[test]
+fn test_duplicate_definitions() {
+    let input = "@Oscar\n\n$gold: 10\n!give\n\n@oscar\n\n$gold: 20\n!GIVE";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec![
+            "Line 6: Actor is already defined [oscar]",
+            "Line 8: Variable is already defined [gold]",
+            "Line 9: Function is already defined [give]",
+        ]
+    );
+    assert_eq!(
+        result.diagnostics[0].related,
+        vec![RelatedSpan {
+            file: None,
+            span: SourceSpan {
+                start: 0,
+                end: 6,
+                line: 1,
+                column: 1,
+            },
+            message: "first defined here".to_string(),
+        }]
+    );
+
+    // The last definition is kept
+    assert_eq!(
+        result.dialogue.variables["gold"],
+        DialogueValue::Number(20.0)
+    );
+}
+
+#[test]
+fn test_project_duplicate_definitions() {
+    let files = [
+        (
+            "main.lex",
+            "$silver: 1\n%include common\n$gold: 5\n# start\nHello",
+        ),
+        ("common.lex", "$gold: 10\n$silver: 2"),
+    ];
+
+    let project = load_test_project("main.lex", &files);
+
+    let diagnostics: Vec<_> = project
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let related: Vec<_> = diagnostic
+                .related
+                .iter()
+                .map(|related| (related.file.as_deref(), related.span.line))
+                .collect();
+
+            (
+                diagnostic.file.as_deref().unwrap_or_default(),
+                diagnostic.span.line,
+                diagnostic.message.as_str(),
+                related,
+            )
+        })
+        .collect();
+
+    // Included definitions are reported at the include directive
+    assert_eq!(
+        diagnostics,
+        vec![
+            (
+                "main.lex",
+                2,
+                "Variable is already defined [silver]",
+                vec![(None, 1), (Some("common.lex"), 2)]
+            ),
+            (
+                "main.lex",
+                3,
+                "Variable is already defined [gold]",
+                vec![(Some("common.lex"), 1)]
+            ),
+        ]
+    );
+
+    assert!(
+        project
+            .render_diagnostic(&project.diagnostics[1])
+            .contains("= note: first defined here (common.lex:1:1)")
+    );
+}
+
+#[test]
+fn test_lint_suppressions() {
+    let input = r"// lex-allow: undefined-variable
+Hello {$gold}
+
+// lex-allow: undefined-actor, missing-lint
+// Other comments are skipped over
+@nobody: Hi
+Bye {$silver}
+
+// lex-allow: undefined-variable
+/// Logs are steps, so are allowed rather than skipped over
+Farewell {$copper}";
+
+    let result = super::functions::parse(input.to_string());
+
+    assert_eq!(
+        diagnostic_messages(&result),
+        vec![
+            "Line 4: Lint not found [missing-lint]",
+            "Line 7: Static variable definition not found [silver]",
+            "Line 11: Static variable definition not found [copper]",
+        ]
+    );
+}
+
+#[test]
+fn test_lint_registry() {
+    let mut lints = LintRegistry::default();
+    assert_eq!(lints.level(DiagnosticCode::UnclosedBlock), LintLevel::Deny);
+    assert_eq!(lints.level(DiagnosticCode::UnusedVariable), LintLevel::Warn);
+
+    lints.set(DiagnosticCode::UnclosedBlock, LintLevel::Warn);
+    assert_eq!(lints.level(DiagnosticCode::UnclosedBlock), LintLevel::Warn);
+
+    // Lints are named as in diagnostics and manifests
+    for code in DiagnosticCode::ALL {
+        assert_eq!(code.as_str().parse::<DiagnosticCode>(), Ok(code));
+    }
+    assert_eq!(
+        "unused-varible".parse::<DiagnosticCode>(),
+        Err(UnknownLintError("unused-varible".to_string()))
+    );
+}
+
+#[test]
+fn test_manifest() {
+    let input = r#"
+[project]
+name = "demo"
+sources = ["dialogues/**/*.lex"]
+entry = "intro"
+
+[[outputs]]
+format = "json"
+path = "build/dialogue.json"
+
+[[outputs]]
+format = "yaml"
+locations = false
+
+[[outputs]]
+format = "ron"
+path = "build/dialogue.ron"
+compiled = true
+
+[locales]
+default = "en"
+available = ["en", "fr"]
+
+[lints]
+undefined-variable = "deny"
+undefined-actor = "allow"
+"#;
+
+    let manifest: Manifest = toml::from_str(input).unwrap();
+
+    assert_eq!(manifest.project.entry.as_deref(), Some("intro"));
+    assert_eq!(
+        manifest.outputs,
+        vec![
+            OutputManifest {
+                format: "json".to_string(),
+                path: Some("build/dialogue.json".to_string()),
+                locations: true,
+                compiled: false,
+            },
+            OutputManifest {
+                format: "yaml".to_string(),
+                path: None,
+                locations: false,
+                compiled: false,
+            },
+            OutputManifest {
+                format: "ron".to_string(),
+                path: Some("build/dialogue.ron".to_string()),
+                locations: true,
+                compiled: true,
+            },
+        ]
+    );
+    assert_eq!(manifest.locales.available, vec!["en", "fr"]);
+
+    let mut result = super::functions::parse("Hello {$gold}, {@oscar.name}".to_string());
+    manifest.apply_lints(&mut result.diagnostics);
+
+    assert_eq!(
+        result
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.severity))
+            .collect::<Vec<_>>(),
+        vec![(DiagnosticCode::UndefinedVariable, Severity::Error)]
+    );
+
+    // Unknown lints are rejected, rather than silently ignored
+    assert!(toml::from_str::<Manifest>("[lints]\nundefined-varible = \"deny\"").is_err());
+}
+
+#[test]
+fn test_manifest_discovery() {
+    let root = std::env::temp_dir().join(format!("lex-manifest-{}", std::process::id()));
+    let nested = root.join("dialogues").join("chapters");
+    std::fs::create_dir_all(&nested).unwrap();
+
+    std::fs::write(
+        root.join(MANIFEST_FILE_NAME),
+        "[project]\nentry = \"intro\"",
+    )
+    .unwrap();
+    std::fs::write(root.join("dialogues/intro.lex"), "# intro\nHello").unwrap();
+    std::fs::write(nested.join("one.lex"), "# one\nHello again").unwrap();
+
+    let manifest_path = Manifest::find(&nested);
+    assert_eq!(manifest_path, Some(root.join(MANIFEST_FILE_NAME)));
+
+    let manifest = Manifest::load(manifest_path.unwrap()).unwrap();
+    assert_eq!(manifest.root, root);
+
+    // Every dialogue file is a source by default
+    let project = manifest.load_project().unwrap();
+    let section_names: Vec<_> = project
+        .dialogue
+        .sections
+        .iter()
+        .map(|section| section.name.as_str())
+        .collect();
+    assert_eq!(section_names, vec!["one", "intro"]);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
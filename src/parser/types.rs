@@ -1,13 +1,19 @@
 use std::collections::HashMap;
+use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
+use super::syntax;
+
 #[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Dialogue {
     // Metadata
     pub actors: HashMap<String, DialogueActor>,
     pub variables: HashMap<String, DialogueValue>,
     pub functions: HashMap<String, DialogueFunction>,
+    /// Where each variable was defined, as variables are stored as just their initial values
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variable_locations: HashMap<String, SourceLocation>,
 
     // Content
     pub sections: Vec<DialogueSection>,
@@ -17,39 +23,440 @@ pub struct Dialogue {
 pub struct DialogueSection {
     pub name: String,
     pub steps: Vec<DialogueStep>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum DialogueStep {
-    Comment(String),
-    LogInfo(String),
-    LogWarning(String),
-    LogError(String),
-    Page(Vec<DialogueLine>),
+    Comment {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    LogInfo {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    LogWarning {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    LogError {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    Page {
+        lines: Vec<DialogueLine>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
     VariableAssign {
         name: String,
-        value: DialogueValue,
+        value: DialogueExpression,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    SectionBounce {
+        target: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    SectionJump {
+        target: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    EndJump {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    TerminateJump {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    Conditional {
+        condition: DialogueExpression,
+        then_steps: Vec<DialogueStep>,
+        else_steps: Vec<DialogueStep>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    Repeat {
+        count: usize,
+        steps: Vec<DialogueStep>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    While {
+        condition: DialogueExpression,
+        steps: Vec<DialogueStep>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    Each {
+        array: String,
+        item: String,
+        steps: Vec<DialogueStep>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
+    FunctionCall {
+        name: String,
+        args: HashMap<String, DialogueValue>,
+        /// Variable to store the function's result in, if any
+        result: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
     },
-    SectionBounce(String),
-    SectionJump(String),
-    EndJump,
-    TerminateJump,
+}
+
+/// Where an element of a dialogue was defined in its source.
+///
+/// Lines and columns are one-based, with columns counted in characters.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    /// Path of the source file, if the source was parsed from a file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Describes an expression which is evaluated against the dialogue's data during playback.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DialogueExpression {
+    /// A literal value, such as `10` or `"some text"`
+    Value(DialogueValue),
+    /// A variable reference, such as `$gold`
+    Variable(String),
+    /// An actor property reference, such as `@oscar.age`
+    ActorProperty { actor: String, property: String },
+    /// An operation on a single operand, such as `not $has_key`
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<DialogueExpression>,
+    },
+    /// An operation on two operands, such as `$gold + 10`
+    Binary {
+        left: Box<DialogueExpression>,
+        operator: BinaryOperator,
+        right: Box<DialogueExpression>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    And,
+    Or,
+}
+
+impl UnaryOperator {
+    /// The operator as written in expressions, such as `not`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOperator::Not => syntax::operators::NOT,
+            UnaryOperator::Negate => syntax::operators::SUBTRACT,
+        }
+    }
+}
+
+impl BinaryOperator {
+    /// The operator as written in expressions, such as `+`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Add => syntax::operators::ADD,
+            BinaryOperator::Subtract => syntax::operators::SUBTRACT,
+            BinaryOperator::Multiply => syntax::operators::MULTIPLY,
+            BinaryOperator::Divide => syntax::operators::DIVIDE,
+            BinaryOperator::Remainder => syntax::operators::REMAINDER,
+            BinaryOperator::Equal => syntax::operators::EQUAL,
+            BinaryOperator::NotEqual => syntax::operators::NOT_EQUAL,
+            BinaryOperator::Less => syntax::operators::LESS,
+            BinaryOperator::LessOrEqual => syntax::operators::LESS_OR_EQUAL,
+            BinaryOperator::Greater => syntax::operators::GREATER,
+            BinaryOperator::GreaterOrEqual => syntax::operators::GREATER_OR_EQUAL,
+            BinaryOperator::And => syntax::operators::AND,
+            BinaryOperator::Or => syntax::operators::OR,
+        }
+    }
 }
 
 /// Describes the content of a given line of dialogue.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum DialogueLine {
-    Text(String),
+    Text {
+        text: DialogueText,
+        /// Metadata for the line, such as mood or camera hints: `[key=value]`
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        annotations: HashMap<String, DialogueValue>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
+    },
     SpeakerText {
         speaker: String,
-        text: String,
+        text: DialogueText,
+        /// Metadata for the line, such as mood or camera hints: `[key=value]`
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        annotations: HashMap<String, DialogueValue>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
     },
     Response {
-        text: String,
+        text: DialogueText,
         pages: Vec<DialogueStep>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        location: Option<SourceLocation>,
     },
 }
 
+/// Text made up of styled spans of literal text and references to dialogue data, which are
+/// substituted with their current values during playback.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DialogueText {
+    pub spans: Vec<TextSpan>,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{file}:{}:{}", self.line, self.column),
+            None => write!(f, "line {}, column {}", self.line, self.column),
+        }
+    }
+}
+
+impl Dialogue {
+    /// Removes the source locations from every definition, section, step and line, such as to
+    /// keep them out of exported data.
+    pub fn strip_source_locations(&mut self) {
+        for actor in self.actors.values_mut() {
+            actor.location = None;
+        }
+
+        for function in self.functions.values_mut() {
+            function.location = None;
+        }
+
+        self.variable_locations.clear();
+
+        for section in &mut self.sections {
+            section.location = None;
+
+            for step in &mut section.steps {
+                step.strip_source_locations();
+            }
+        }
+    }
+}
+
+impl DialogueStep {
+    /// Gets where the step was defined in source, if known.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            DialogueStep::Comment { location, .. }
+            | DialogueStep::LogInfo { location, .. }
+            | DialogueStep::LogWarning { location, .. }
+            | DialogueStep::LogError { location, .. }
+            | DialogueStep::Page { location, .. }
+            | DialogueStep::VariableAssign { location, .. }
+            | DialogueStep::SectionBounce { location, .. }
+            | DialogueStep::SectionJump { location, .. }
+            | DialogueStep::EndJump { location }
+            | DialogueStep::TerminateJump { location }
+            | DialogueStep::Conditional { location, .. }
+            | DialogueStep::Repeat { location, .. }
+            | DialogueStep::While { location, .. }
+            | DialogueStep::Each { location, .. }
+            | DialogueStep::FunctionCall { location, .. } => location.as_ref(),
+        }
+    }
+
+    /// Sets where the step was defined in source.
+    pub fn set_location(&mut self, new_location: Option<SourceLocation>) {
+        match self {
+            DialogueStep::Comment { location, .. }
+            | DialogueStep::LogInfo { location, .. }
+            | DialogueStep::LogWarning { location, .. }
+            | DialogueStep::LogError { location, .. }
+            | DialogueStep::Page { location, .. }
+            | DialogueStep::VariableAssign { location, .. }
+            | DialogueStep::SectionBounce { location, .. }
+            | DialogueStep::SectionJump { location, .. }
+            | DialogueStep::EndJump { location }
+            | DialogueStep::TerminateJump { location }
+            | DialogueStep::Conditional { location, .. }
+            | DialogueStep::Repeat { location, .. }
+            | DialogueStep::While { location, .. }
+            | DialogueStep::Each { location, .. }
+            | DialogueStep::FunctionCall { location, .. } => *location = new_location,
+        }
+    }
+
+    /// Removes the source locations from the step and any steps or lines nested within it.
+    pub fn strip_source_locations(&mut self) {
+        self.set_location(None);
+
+        match self {
+            DialogueStep::Page { lines, .. } => {
+                for line in lines {
+                    line.strip_source_locations();
+                }
+            }
+
+            DialogueStep::Conditional {
+                then_steps,
+                else_steps,
+                ..
+            } => {
+                for step in then_steps.iter_mut().chain(else_steps) {
+                    step.strip_source_locations();
+                }
+            }
+
+            DialogueStep::Repeat { steps, .. }
+            | DialogueStep::While { steps, .. }
+            | DialogueStep::Each { steps, .. } => {
+                for step in steps {
+                    step.strip_source_locations();
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
+
+impl DialogueLine {
+    /// Gets where the line was defined in source, if known.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            DialogueLine::Text { location, .. }
+            | DialogueLine::SpeakerText { location, .. }
+            | DialogueLine::Response { location, .. } => location.as_ref(),
+        }
+    }
+
+    /// Sets where the line was defined in source.
+    pub fn set_location(&mut self, new_location: Option<SourceLocation>) {
+        match self {
+            DialogueLine::Text { location, .. }
+            | DialogueLine::SpeakerText { location, .. }
+            | DialogueLine::Response { location, .. } => *location = new_location,
+        }
+    }
+
+    /// Removes the source locations from the line and any steps nested within it.
+    pub fn strip_source_locations(&mut self) {
+        self.set_location(None);
+
+        if let DialogueLine::Response { pages, .. } = self {
+            for step in pages {
+                step.strip_source_locations();
+            }
+        }
+    }
+}
+
+impl DialogueText {
+    /// Creates text made up of a single unstyled literal span.
+    pub fn literal(text: impl Into<String>) -> Self {
+        Self {
+            spans: vec![TextSpan::plain(TextSegment::Literal(text.into()))],
+        }
+    }
+
+    /// Gets the text without styling, with any data references in their original syntax.
+    pub fn plain_text(&self) -> String {
+        self.spans
+            .iter()
+            .map(|span| match &span.content {
+                TextSegment::Literal(literal) => literal.clone(),
+                TextSegment::Variable(name) => format!("{{${name}}}"),
+                TextSegment::ActorProperty { actor, property } => {
+                    format!("{{@{actor}.{property}}}")
+                }
+            })
+            .collect()
+    }
+}
+
+/// A piece of text content along with the style it is displayed in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextSpan {
+    pub content: TextSegment,
+    #[serde(default, skip_serializing_if = "TextStyle::is_plain")]
+    pub style: TextStyle,
+}
+
+impl TextSpan {
+    /// Creates a span with no styling.
+    pub fn plain(content: TextSegment) -> Self {
+        Self {
+            content,
+            style: TextStyle::default(),
+        }
+    }
+}
+
+/// Describes how a span of text is displayed.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextStyle {
+    /// `**text**`
+    #[serde(default)]
+    pub bold: bool,
+    /// `*text*`
+    #[serde(default)]
+    pub italic: bool,
+    /// Custom tags such as `<shake>` or `<color=red>`, from outermost to innermost
+    #[serde(default)]
+    pub tags: Vec<TextTag>,
+}
+
+impl TextStyle {
+    /// Whether the style has no styling applied.
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A custom markup tag: `<name>` or `<name=value>`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextTag {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TextSegment {
+    /// Literal text
+    Literal(String),
+    /// A variable reference: `{$variable_name}`
+    Variable(String),
+    /// An actor property reference: `{@actor_id.property}`
+    ActorProperty { actor: String, property: String },
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum DialogueValue {
     Text(String),
@@ -58,14 +465,59 @@ pub enum DialogueValue {
     Array(Vec<String>),
 }
 
+impl fmt::Display for DialogueValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DialogueValue::Text(text) => write!(f, "{text}"),
+            DialogueValue::Number(number) => write!(f, "{number}"),
+            DialogueValue::Boolean(boolean) => write!(f, "{boolean}"),
+            DialogueValue::Array(items) => write!(f, "{}", items.join(", ")),
+        }
+    }
+}
+
+impl DialogueValue {
+    /// Gets the name of the value's type, such as for describing a mismatch between types.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            DialogueValue::Text(_) => "text",
+            DialogueValue::Number(_) => "number",
+            DialogueValue::Boolean(_) => "boolean",
+            DialogueValue::Array(_) => "array",
+        }
+    }
+
+    /// Whether the value is of the same type as another, regardless of their contents.
+    pub fn is_same_type(&self, other: &DialogueValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Whether the value counts as `true` when used as a condition.
+    ///
+    /// Booleans are used as-is, numbers are truthy when non-zero, and text and arrays are truthy
+    /// when non-empty.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            DialogueValue::Text(text) => !text.is_empty(),
+            DialogueValue::Number(number) => *number != 0.0,
+            DialogueValue::Boolean(boolean) => *boolean,
+            DialogueValue::Array(items) => !items.is_empty(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DialogueFunction {
     pub args: Option<HashMap<String, DialogueValue>>,
     pub result: Option<DialogueValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DialogueActor {
     pub name: String,
     pub properties: HashMap<String, DialogueValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<SourceLocation>,
 }
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::DialogueValue;
+
+/// Game logic run by a [`DialogueRunner`](crate::DialogueRunner) when dialogue calls a function.
+///
+/// Handlers are given the call's arguments, with the defaults of any left out filled in from the
+/// function's definition, and return the function's result if it has one. Returning `None` leaves
+/// the result as the definition's default.
+///
+/// Closures taking the arguments are handlers too.
+///
+/// # Example
+/// ```
+/// use dialogue_syntax::{DialogueRunner, DialogueValue, compile, parse};
+///
+/// let dialogue = parse("$coins: 0\n!count_coins: 0\n\n# intro\n$coins = !count_coins()".to_string());
+/// let mut runner = DialogueRunner::new(compile(&dialogue.dialogue).unwrap());
+///
+/// runner.register_function("count_coins", |_: &_| Some(DialogueValue::Number(3.0)));
+/// runner.next();
+///
+/// assert_eq!(runner.variable("coins"), Some(&DialogueValue::Number(3.0)));
+/// ```
+pub trait FunctionHandler {
+    fn call(&mut self, args: &HashMap<String, DialogueValue>) -> Option<DialogueValue>;
+}
+
+impl<F> FunctionHandler for F
+where
+    F: FnMut(&HashMap<String, DialogueValue>) -> Option<DialogueValue>,
+{
+    fn call(&mut self, args: &HashMap<String, DialogueValue>) -> Option<DialogueValue> {
+        self(args)
+    }
+}
+
+/// Handlers registered with a runner, by the index of the function they handle.
+#[derive(Default)]
+pub(crate) struct FunctionHandlers {
+    handlers: HashMap<usize, Box<dyn FunctionHandler>>,
+}
+
+impl FunctionHandlers {
+    pub(crate) fn insert(&mut self, function: usize, handler: Box<dyn FunctionHandler>) {
+        self.handlers.insert(function, handler);
+    }
+
+    pub(crate) fn get_mut(&mut self, function: usize) -> Option<&mut Box<dyn FunctionHandler>> {
+        self.handlers.get_mut(&function)
+    }
+}
+
+impl fmt::Debug for FunctionHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
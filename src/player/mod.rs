@@ -1,165 +1,149 @@
 //! Player module for interactive dialogue playback.
 
-use crate::{Dialogue, DialogueLine, DialogueSection, DialogueStep};
-
-#[derive(Debug, Clone, PartialEq)]
-struct DialogueState {
-    section: DialogueSection,
-    step: DialogueStep,
-}
-
-/// Plays back a dialogue interactively using basic CLI.
-pub fn play(mut dialogue: Dialogue) {
-    let first_section = dialogue
-        .sections
-        .first()
-        .expect("No sections found in dialogue");
-
-    let first_step = first_section
-        .steps
-        .first()
-        .expect("No pages found in first section");
-
-    let first_state = DialogueState {
-        section: first_section.clone(),
-        step: first_step.clone(),
-    };
-
-    let mut dialogue_stack = vec![first_state];
+mod functions;
+mod runner;
+#[cfg(test)]
+mod tests;
+
+pub use functions::FunctionHandler;
+pub use runner::*;
+
+use std::io::Write;
+
+use crate::{DialogueText, TextSegment, TextStyle};
+
+/// Plays back dialogue interactively using basic CLI, continuing from wherever the runner is.
+///
+/// The given callback is called before each choice is read, such as to save progress.
+pub fn play(mut runner: DialogueRunner, mut on_choice: impl FnMut(&DialogueRunner)) {
+    loop {
+        match runner.next() {
+            DialogueEvent::Line {
+                speaker_name, text, ..
+            } => {
+                match speaker_name {
+                    Some(speaker_name) => println!("{speaker_name}: {}", render_text(&text)),
+                    None => println!("{}", render_text(&text)),
+                }
 
-    while let Some(current_state) = dialogue_stack.pop() {
-        let mut next_state = None;
+                // Simulate line playback
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
 
-        match &current_state.step {
-            DialogueStep::Comment(_) => {}
+            DialogueEvent::Choices { choices } => {
+                for (index, text) in choices.iter().enumerate() {
+                    println!("  {}. {}", index + 1, render_text(text));
+                }
 
-            DialogueStep::LogInfo(text) => {
-                println!("{text}");
-            }
+                on_choice(&runner);
 
-            DialogueStep::LogWarning(text) => {
-                println!("{text}");
-            }
+                let Some(choice) = read_choice(choices.len()) else {
+                    break;
+                };
 
-            DialogueStep::LogError(text) => {
-                eprintln!("{text}");
+                runner
+                    .choose(choice)
+                    .expect("Choice should be within the responses on offer");
             }
 
-            DialogueStep::VariableAssign { name, value } => {
-                if dialogue.variables.contains_key(name) {
-                    dialogue.variables.remove(name);
-                } else {
-                    eprintln!("Variable assignment not pre-existing: {name}");
-                }
+            DialogueEvent::FunctionCall { name, args } => {
+                // Without a game to call into, log the call and leave the default result
+                let mut call_args = args
+                    .iter()
+                    .map(|(arg_name, arg_value)| format!("{arg_name}={arg_value}"))
+                    .collect::<Vec<_>>();
+                call_args.sort();
 
-                dialogue.variables.insert(name.clone(), value.clone());
+                println!("Called: !{name}({})", call_args.join(", "));
             }
 
-            DialogueStep::Page(lines) => {
-                let output_lines = lines.iter().map(|line| match line {
-                    DialogueLine::Text(text) => text.clone(),
+            DialogueEvent::Log { level, message } => match level {
+                LogLevel::Error => eprintln!("{message}"),
+                LogLevel::Info | LogLevel::Warning => println!("{message}"),
+            },
 
-                    DialogueLine::SpeakerText { speaker, text } => {
-                        let speaker_name = dialogue
-                            .actors
-                            .get(speaker)
-                            .map_or(speaker, |actor| &actor.name);
-
-                        format!("{speaker_name}: {text}")
-                    }
+            DialogueEvent::End => break,
+        }
+    }
 
-                    DialogueLine::Response { text, pages: _ } => {
-                        format!("- {text}")
-                    }
-                });
+    println!("Playback completed.");
+}
 
-                let output = output_lines.collect::<Vec<_>>().join("\n");
+/// Reads the player's choice of response from stdin as a zero-based index, prompting again until
+/// a valid choice is given.
+///
+/// Returns `None` if stdin is closed or cannot be read, ending playback.
+fn read_choice(response_count: usize) -> Option<usize> {
+    loop {
+        print!("> ");
+        std::io::stdout().flush().ok()?;
 
-                println!("{output}");
-            }
+        let mut input = String::new();
 
-            DialogueStep::SectionJump(section_name) => {
-                next_state = get_section_state_by_name(section_name, &dialogue);
-            }
+        if std::io::stdin().read_line(&mut input).ok()? == 0 {
+            return None;
+        }
 
-            DialogueStep::SectionBounce(section_name) => {
-                next_state = get_section_state_by_name(section_name, &dialogue);
+        match input.trim().parse::<usize>() {
+            Ok(choice) if (1..=response_count).contains(&choice) => return Some(choice - 1),
+            _ => eprintln!("Please choose a response from 1 to {response_count}"),
+        }
+    }
+}
 
-                if next_state.is_some() {
-                    if let Some(next_step) = get_next_step_state(&current_state, &dialogue) {
-                        dialogue_stack.push(next_step);
-                    }
+/// Renders resolved text, applying styles as ANSI escape codes.
+fn render_text(text: &DialogueText) -> String {
+    text.spans
+        .iter()
+        .map(|span| {
+            let content = match &span.content {
+                TextSegment::Literal(literal) => literal.clone(),
+                // Resolved text only holds literals, but show any references as written
+                TextSegment::Variable(name) => format!("{{${name}}}"),
+                TextSegment::ActorProperty { actor, property } => {
+                    format!("{{@{actor}.{property}}}")
                 }
-            }
+            };
 
-            DialogueStep::EndJump => {
-                continue;
-            }
+            let style_codes = get_ansi_style_codes(&span.style);
 
-            DialogueStep::TerminateJump => {
-                break;
+            if style_codes.is_empty() {
+                content
+            } else {
+                format!("\x1b[{}m{content}\x1b[0m", style_codes.join(";"))
             }
-        }
+        })
+        .collect()
+}
 
-        // Simulate line playback
-        std::thread::sleep(std::time::Duration::from_millis(500));
+/// Gets the ANSI SGR codes for a text style. Tags without a terminal equivalent are ignored.
+fn get_ansi_style_codes(style: &TextStyle) -> Vec<&'static str> {
+    let mut codes = Vec::new();
 
-        if let Some(next_state) = next_state.or_else(|| get_next_step_state(&current_state, &dialogue)) {
-            dialogue_stack.push(next_state);
-        }
+    if style.bold {
+        codes.push("1");
     }
 
-    println!("Playback completed.");
-}
-
-/// Gets the next sequential step in the dialogue as a state, if it exists.
-fn get_next_step_state(
-    current_state: &DialogueState,
-    dialogue: &Dialogue,
-) -> Option<DialogueState> {
-    // Move to the current section's next step if it exists
-    if let Some(next_step) = current_state
-        .section
-        .steps
-        .iter()
-        .skip_while(|&l| *l != current_state.step)
-        .nth(1)
-    {
-        return Some(DialogueState {
-            section: current_state.section.clone(),
-            step: next_step.clone(),
-        });
+    if style.italic {
+        codes.push("3");
     }
 
-    // Move to the subsequent section if it exists
-    if let Some(next_section) = dialogue
-        .sections
-        .iter()
-        .skip_while(|&s| *s != current_state.section)
-        .nth(1)
-    {
-        return Some(DialogueState {
-            section: next_section.clone(),
-            step: next_section.steps.first().unwrap().clone(),
-        });
+    for tag in &style.tags {
+        let code = match (tag.name.as_str(), tag.value.as_deref()) {
+            ("color", Some("black")) => "30",
+            ("color", Some("red")) => "31",
+            ("color", Some("green")) => "32",
+            ("color", Some("yellow")) => "33",
+            ("color", Some("blue")) => "34",
+            ("color", Some("magenta")) => "35",
+            ("color", Some("cyan")) => "36",
+            ("color", Some("white")) => "37",
+            ("u", None) => "4",
+            _ => continue,
+        };
+
+        codes.push(code);
     }
 
-    None
-}
-
-fn get_section_state_by_name(section_name: &str, dialogue: &Dialogue) -> Option<DialogueState> {
-    let Some(new_section) = dialogue.sections.iter().find(|s| s.name == *section_name) else {
-        eprintln!("Section not found: {section_name}");
-        return None;
-    };
-
-    let Some(new_page) = new_section.steps.first() else {
-        eprintln!("Section has no pages: {section_name}");
-        return None;
-    };
-
-    Some(DialogueState {
-        section: new_section.clone(),
-        step: new_page.clone(),
-    })
+    codes
 }
@@ -0,0 +1,912 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::functions::FunctionHandlers;
+
+use crate::{
+    BinaryOperator, CompiledDialogue, CompiledExpression, CompiledSection, CompiledSegment,
+    CompiledStep, CompiledText, DialogueText, DialogueValue, FunctionHandler, TextSegment,
+    TextSpan, UnaryOperator,
+};
+
+/// Most steps a single call to [`DialogueRunner::next`] plays without producing an event, beyond
+/// which the dialogue is assumed to be stuck in a loop and is ended.
+const MAX_STEPS_WITHOUT_EVENT: usize = 100_000;
+
+/// Something for the game to present or act upon as dialogue plays.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DialogueEvent {
+    /// A line of text, with any data references substituted with their current values
+    Line {
+        /// Id of the actor speaking, if any
+        speaker: Option<String>,
+        /// Display name of the actor speaking, falling back to their id if they aren't defined
+        speaker_name: Option<String>,
+        text: DialogueText,
+        annotations: HashMap<String, DialogueValue>,
+        /// Whether the line is the first of its page, rather than continuing the previous line's
+        starts_page: bool,
+    },
+    /// Responses to choose between with [`DialogueRunner::choose`] before dialogue continues
+    Choices { choices: Vec<DialogueText> },
+    /// A function without a registered handler for the game to run, with its default arguments
+    /// filled in
+    FunctionCall {
+        name: String,
+        args: HashMap<String, DialogueValue>,
+    },
+    /// A message logged by the dialogue, or a problem encountered while running it
+    Log { level: LogLevel, message: String },
+    /// Dialogue has finished, either by running out of steps or terminating
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Error choosing a response
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChoiceError {
+    /// No choice is waiting to be made
+    NoChoices,
+    /// The index is beyond the responses on offer
+    OutOfRange { index: usize, count: usize },
+}
+
+impl fmt::Display for ChoiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChoiceError::NoChoices => write!(f, "No choice is waiting to be made"),
+            ChoiceError::OutOfRange { index, count } => {
+                write!(f, "Choice {index} is out of range of {count} responses")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChoiceError {}
+
+/// A branch of a step which holds a nested block of steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Branch {
+    /// Steps played when a conditional's condition is met
+    Then,
+    /// Steps played when a conditional's condition isn't met
+    Else,
+    /// Steps of a loop
+    Body,
+    /// Steps nested under the page's response with the given index, counting only responses
+    Response(usize),
+}
+
+/// Position of a block of steps within the dialogue, as a section and the branches leading from
+/// its steps to the block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct BlockPath {
+    section: usize,
+    nesting: Vec<(usize, Branch)>,
+}
+
+/// What happens once a block has finished playing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum BlockRepeat {
+    /// The block is left, returning to the step after the one it is nested in
+    Once,
+    /// The block is played again the given number of times
+    Times(usize),
+    /// The block is played again for each remaining item, which are held in reverse order
+    Each { item: usize, items: Vec<String> },
+}
+
+/// Cursor into a block of steps, pointing at the next step to play.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct BlockFrame {
+    path: BlockPath,
+    step: usize,
+    repeat: BlockRepeat,
+}
+
+/// Blocks being played since a section was entered by a bounce, or as the entry section.
+///
+/// The outermost block is always a section, with any blocks nested in it on top.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct CallFrame {
+    blocks: Vec<BlockFrame>,
+}
+
+impl BlockFrame {
+    /// Creates a frame at the start of the given section.
+    fn section(section: usize) -> Self {
+        Self {
+            path: BlockPath {
+                section,
+                nesting: Vec::new(),
+            },
+            step: 0,
+            repeat: BlockRepeat::Once,
+        }
+    }
+}
+
+impl CallFrame {
+    /// Creates a call frame at the start of the given section.
+    fn section(section: usize) -> Self {
+        Self {
+            blocks: vec![BlockFrame::section(section)],
+        }
+    }
+}
+
+/// Responses waiting on a choice, along with the page they were offered from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PendingChoice {
+    choices: Vec<DialogueText>,
+    /// Index of the page step within the current block
+    page_step: usize,
+}
+
+/// Runs a compiled dialogue step by step, producing events for a game to present.
+///
+/// The runner holds the current value of each variable, so assignments made as it plays can be
+/// read back with [`variable`](Self::variable), or made by the game with
+/// [`set_variable`](Self::set_variable). Game logic can be bound to the dialogue's functions with
+/// [`register_function`](Self::register_function).
+///
+/// # Example
+/// ```
+/// use dialogue_syntax::{DialogueEvent, DialogueRunner, compile, parse};
+///
+/// let dialogue = parse("# intro\nHello!\n- Hi\n- Bye".to_string()).dialogue;
+/// let mut runner = DialogueRunner::new(compile(&dialogue).unwrap());
+///
+/// loop {
+///     match runner.next() {
+///         DialogueEvent::Line { text, .. } => println!("{}", text.plain_text()),
+///         DialogueEvent::Choices { .. } => runner.choose(0).unwrap(),
+///         DialogueEvent::End => break,
+///         _ => {}
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DialogueRunner {
+    program: CompiledDialogue,
+    /// Current value of each variable, by slot
+    variables: Vec<DialogueValue>,
+    /// Number of times each section has been entered, by index
+    visit_counts: Vec<usize>,
+    /// Sections being played, with the most recent bounce on top
+    call_stack: Vec<CallFrame>,
+    /// Events produced by the current step, yet to be returned
+    events: VecDeque<DialogueEvent>,
+    choice: Option<PendingChoice>,
+    handlers: FunctionHandlers,
+}
+
+/// Progress of a runner through its dialogue, which can be saved and later restored with
+/// [`DialogueRunner::restore`] to resume dialogue where it left off.
+///
+/// # Example
+/// ```
+/// use dialogue_syntax::{DialogueRunner, compile, parse};
+///
+/// let dialogue = parse("# intro\nHello!\n- Hi\n- Bye".to_string()).dialogue;
+/// let program = compile(&dialogue).unwrap();
+///
+/// let mut runner = DialogueRunner::new(program.clone());
+/// runner.next();
+///
+/// let saved = serde_json::to_string(&runner.save_state()).unwrap();
+/// let state = serde_json::from_str(&saved).unwrap();
+///
+/// let restored = DialogueRunner::restore(program, state).unwrap();
+/// assert_eq!(restored.save_state(), runner.save_state());
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunnerState {
+    /// Hash of the compiled dialogue the state was saved from
+    pub dialogue_hash: u32,
+    variables: Vec<DialogueValue>,
+    visit_counts: Vec<usize>,
+    call_stack: Vec<CallFrame>,
+    events: VecDeque<DialogueEvent>,
+    choice: Option<PendingChoice>,
+}
+
+/// Error restoring a runner from a saved state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The dialogue has changed since the state was saved
+    DialogueChanged { saved_hash: u32, current_hash: u32 },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::DialogueChanged {
+                saved_hash,
+                current_hash,
+            } => write!(
+                f,
+                "Dialogue has changed since the state was saved ({saved_hash:08x} != {current_hash:08x})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl DialogueRunner {
+    /// Creates a runner which starts from the first section of the dialogue.
+    pub fn new(program: CompiledDialogue) -> Self {
+        let entry = (!program.sections.is_empty()).then_some(0);
+
+        Self::start(program, entry)
+    }
+
+    /// Creates a runner which starts from the named section of the dialogue.
+    ///
+    /// If the section doesn't exist, the runner logs an error and ends.
+    pub fn with_entry(program: CompiledDialogue, section_name: &str) -> Self {
+        let entry = program.section_index(section_name);
+        let mut runner = Self::start(program, entry);
+
+        if entry.is_none() {
+            runner.log(
+                LogLevel::Error,
+                format!("Section not found: {section_name}"),
+            );
+        }
+
+        runner
+    }
+
+    /// Recreates a runner from a saved state, continuing dialogue where it left off.
+    ///
+    /// The state must have been saved from the same compiled dialogue, so changes to the dialogue
+    /// since it was saved are reported rather than resuming from the wrong place. Function
+    /// handlers aren't part of the state, so need registering again.
+    pub fn restore(program: CompiledDialogue, state: RunnerState) -> Result<Self, StateError> {
+        if state.dialogue_hash != program.hash {
+            return Err(StateError::DialogueChanged {
+                saved_hash: state.dialogue_hash,
+                current_hash: program.hash,
+            });
+        }
+
+        Ok(Self {
+            program,
+            variables: state.variables,
+            visit_counts: state.visit_counts,
+            call_stack: state.call_stack,
+            events: state.events,
+            choice: state.choice,
+            handlers: FunctionHandlers::default(),
+        })
+    }
+
+    /// Creates a runner which starts from the given section, or ends immediately if none is given.
+    fn start(program: CompiledDialogue, entry: Option<usize>) -> Self {
+        let variables = program
+            .variables
+            .iter()
+            .map(|variable| variable.value.clone())
+            .collect();
+
+        let mut runner = Self {
+            visit_counts: vec![0; program.sections.len()],
+            program,
+            variables,
+            call_stack: Vec::new(),
+            events: VecDeque::new(),
+            choice: None,
+            handlers: FunctionHandlers::default(),
+        };
+
+        if let Some(section) = entry {
+            runner.visit_counts[section] += 1;
+            runner.call_stack.push(CallFrame::section(section));
+        }
+
+        runner
+    }
+
+    /// Advances the dialogue, returning the next event.
+    ///
+    /// While a choice is waiting to be made, the same choices are returned until one is made with
+    /// [`choose`](Self::choose). Once dialogue has finished, [`DialogueEvent::End`] is returned
+    /// indefinitely.
+    ///
+    /// Dialogue which plays too many steps without producing an event, such as a `WHILE` loop
+    /// whose body only assigns variables, logs an error and ends rather than never returning.
+    // Not an iterator, as dialogue ends with an event rather than `None`
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> DialogueEvent {
+        let mut steps = 0;
+
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return event;
+            }
+
+            if let Some(choice) = &self.choice {
+                return DialogueEvent::Choices {
+                    choices: choice.choices.clone(),
+                };
+            }
+
+            if self.call_stack.is_empty() {
+                return DialogueEvent::End;
+            }
+
+            steps += 1;
+            if steps > MAX_STEPS_WITHOUT_EVENT {
+                self.call_stack.clear();
+
+                return DialogueEvent::Log {
+                    level: LogLevel::Error,
+                    message: format!(
+                        "Dialogue played {MAX_STEPS_WITHOUT_EVENT} steps without producing an event, so is assumed to be stuck in a loop"
+                    ),
+                };
+            }
+
+            // Sections are moved out while a step plays, so the step can be borrowed while the
+            // runner's state changes
+            let sections = std::mem::take(&mut self.program.sections);
+            self.advance(&sections);
+            self.program.sections = sections;
+        }
+    }
+
+    /// Chooses one of the responses on offer by its zero-based index, continuing dialogue with
+    /// the steps nested under it.
+    pub fn choose(&mut self, index: usize) -> Result<(), ChoiceError> {
+        let Some(choice) = &self.choice else {
+            return Err(ChoiceError::NoChoices);
+        };
+
+        if index >= choice.choices.len() {
+            return Err(ChoiceError::OutOfRange {
+                index,
+                count: choice.choices.len(),
+            });
+        }
+
+        let page_step = choice.page_step;
+        self.choice = None;
+
+        self.enter_block(page_step, Branch::Response(index), BlockRepeat::Once);
+
+        Ok(())
+    }
+
+    /// Whether dialogue has finished, with no steps left to play.
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty() && self.choice.is_none() && self.call_stack.is_empty()
+    }
+
+    /// Gets the compiled dialogue being run.
+    pub fn program(&self) -> &CompiledDialogue {
+        &self.program
+    }
+
+    /// Gets the current value of a variable.
+    pub fn variable(&self, name: &str) -> Option<&DialogueValue> {
+        let slot = self.program.variable_slot(name)?;
+        self.variables.get(slot)
+    }
+
+    /// Sets the value of a variable, such as to the result of a function the game has run.
+    ///
+    /// Returns `false` if the dialogue defines no such variable.
+    pub fn set_variable(&mut self, name: &str, value: DialogueValue) -> bool {
+        let Some(slot) = self.program.variable_slot(name) else {
+            return false;
+        };
+
+        self.variables[slot] = value;
+        true
+    }
+
+    /// Registers a handler to run whenever dialogue calls the named function, in place of
+    /// producing a [`DialogueEvent::FunctionCall`] event.
+    ///
+    /// Returns `false` if the dialogue defines no such function.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        handler: impl FunctionHandler + 'static,
+    ) -> bool {
+        let name = name.to_lowercase();
+
+        let Some(function) = self
+            .program
+            .functions
+            .iter()
+            .position(|function| function.name == name)
+        else {
+            return false;
+        };
+
+        self.handlers.insert(function, Box::new(handler));
+        true
+    }
+
+    /// Gets the number of times the named section has been entered, whether as the entry
+    /// section, by navigating to it, or by running on into it from the previous section.
+    pub fn visit_count(&self, section_name: &str) -> usize {
+        self.program
+            .section_index(section_name)
+            .map_or(0, |section| self.visit_counts[section])
+    }
+
+    /// Saves the runner's progress through its dialogue, to be restored with
+    /// [`restore`](Self::restore).
+    pub fn save_state(&self) -> RunnerState {
+        RunnerState {
+            dialogue_hash: self.program.hash,
+            variables: self.variables.clone(),
+            visit_counts: self.visit_counts.clone(),
+            call_stack: self.call_stack.clone(),
+            events: self.events.clone(),
+            choice: self.choice.clone(),
+        }
+    }
+
+    /// Plays the step under the cursor and moves the cursor on, or finishes the current block if
+    /// it has no steps left.
+    fn advance(&mut self, sections: &[CompiledSection]) {
+        let Some(frame) = self
+            .call_stack
+            .last_mut()
+            .and_then(|call| call.blocks.last_mut())
+        else {
+            self.call_stack.pop();
+            return;
+        };
+
+        let steps = get_block_steps(&frame.path, sections);
+
+        let Some(step) = steps.get(frame.step) else {
+            self.finish_block(sections);
+            return;
+        };
+
+        let step_index = frame.step;
+        frame.step += 1;
+
+        self.run_step(step, step_index);
+    }
+
+    /// Handles the current block running out of steps, by repeating it, returning to the block it
+    /// is nested in, or moving on to the next section.
+    fn finish_block(&mut self, sections: &[CompiledSection]) {
+        let Some(call) = self.call_stack.last_mut() else {
+            return;
+        };
+        let nested = call.blocks.len() > 1;
+        let Some(frame) = call.blocks.last_mut() else {
+            return;
+        };
+
+        match &mut frame.repeat {
+            BlockRepeat::Times(remaining) if *remaining > 0 => {
+                *remaining -= 1;
+                frame.step = 0;
+                return;
+            }
+            BlockRepeat::Each { item, items } => {
+                if let Some(value) = items.pop() {
+                    frame.step = 0;
+
+                    self.variables[*item] = DialogueValue::Text(value);
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        if nested {
+            call.blocks.pop();
+            return;
+        }
+
+        // Sections run on into the next section, or return from their bounce after the last
+        let next_section = frame.path.section + 1;
+
+        if next_section < sections.len() {
+            *frame = BlockFrame::section(next_section);
+            self.visit_counts[next_section] += 1;
+        } else {
+            self.call_stack.pop();
+        }
+    }
+
+    /// Starts playing a block nested in a step of the current block.
+    fn enter_block(&mut self, step_index: usize, branch: Branch, repeat: BlockRepeat) {
+        let Some(call) = self.call_stack.last_mut() else {
+            return;
+        };
+        let Some(frame) = call.blocks.last() else {
+            return;
+        };
+
+        let mut path = frame.path.clone();
+        path.nesting.push((step_index, branch));
+
+        call.blocks.push(BlockFrame {
+            path,
+            step: 0,
+            repeat,
+        });
+    }
+
+    /// Plays a single step, queuing any events it produces.
+    ///
+    /// The cursor has already moved past the step, so steps only move it when they navigate
+    /// elsewhere.
+    fn run_step(&mut self, step: &CompiledStep, step_index: usize) {
+        match step {
+            CompiledStep::LogInfo(text) => {
+                self.log(LogLevel::Info, text.clone());
+            }
+
+            CompiledStep::LogWarning(text) => {
+                self.log(LogLevel::Warning, text.clone());
+            }
+
+            CompiledStep::LogError(text) => {
+                self.log(LogLevel::Error, text.clone());
+            }
+
+            CompiledStep::VariableAssign { variable, value } => {
+                match evaluate_expression(value, &self.variables) {
+                    Ok(value) => self.variables[*variable] = value,
+                    Err(error) => self.log(LogLevel::Error, error),
+                }
+            }
+
+            CompiledStep::Page { lines, responses } => {
+                for (line_index, line) in lines.iter().enumerate() {
+                    let text = self.resolve_text(&line.text);
+
+                    self.events.push_back(DialogueEvent::Line {
+                        speaker: line.speaker.as_ref().map(|speaker| speaker.id.clone()),
+                        speaker_name: line.speaker.as_ref().map(|speaker| speaker.name.clone()),
+                        text,
+                        annotations: line.annotations.clone(),
+                        starts_page: line_index == 0,
+                    });
+                }
+
+                // Playback continues once a response is chosen
+                if !responses.is_empty() {
+                    let choices = responses
+                        .iter()
+                        .map(|response| self.resolve_text(&response.text))
+                        .collect();
+
+                    self.choice = Some(PendingChoice {
+                        choices,
+                        page_step: step_index,
+                    });
+                }
+            }
+
+            CompiledStep::SectionJump(section) => {
+                // Jumps leave every block of the current section behind
+                if let Some(call) = self.call_stack.last_mut() {
+                    *call = CallFrame::section(*section);
+                    self.visit_counts[*section] += 1;
+                }
+            }
+
+            CompiledStep::SectionBounce(section) => {
+                self.call_stack.push(CallFrame::section(*section));
+                self.visit_counts[*section] += 1;
+            }
+
+            CompiledStep::EndJump => {
+                self.call_stack.pop();
+            }
+
+            CompiledStep::TerminateJump => {
+                self.call_stack.clear();
+            }
+
+            CompiledStep::Conditional { condition, .. } => {
+                let branch = if self.evaluate_condition(condition) {
+                    Branch::Then
+                } else {
+                    Branch::Else
+                };
+
+                self.enter_block(step_index, branch, BlockRepeat::Once);
+            }
+
+            CompiledStep::Repeat { count, .. } => {
+                if *count > 0 {
+                    self.enter_block(step_index, Branch::Body, BlockRepeat::Times(count - 1));
+                }
+            }
+
+            CompiledStep::While { condition, steps } => {
+                if !steps.is_empty() && self.evaluate_condition(condition) {
+                    // Revisit the loop once its steps are done to re-evaluate the condition
+                    if let Some(frame) = self
+                        .call_stack
+                        .last_mut()
+                        .and_then(|call| call.blocks.last_mut())
+                    {
+                        frame.step = step_index;
+                    }
+
+                    self.enter_block(step_index, Branch::Body, BlockRepeat::Once);
+                }
+            }
+
+            CompiledStep::FunctionCall {
+                function,
+                args,
+                result,
+            } => {
+                let value = match self.handlers.get_mut(*function) {
+                    Some(handler) => {
+                        let value = handler.call(args);
+                        self.check_function_result(*function, value)
+                    }
+
+                    None => {
+                        let function = &self.program.functions[*function];
+
+                        self.events.push_back(DialogueEvent::FunctionCall {
+                            name: function.name.clone(),
+                            args: args.clone(),
+                        });
+
+                        // The game may replace the default result once it has run the call
+                        function.result.clone()
+                    }
+                };
+
+                if let Some(variable) = result
+                    && let Some(value) = value
+                {
+                    self.variables[*variable] = value;
+                }
+            }
+
+            CompiledStep::Each { array, item, .. } => {
+                let mut items = match &self.variables[*array] {
+                    DialogueValue::Array(items) => items.clone(),
+                    _ => {
+                        let name = &self.program.variables[*array].name;
+                        self.log(LogLevel::Error, format!("Variable is not an array: {name}"));
+                        Vec::new()
+                    }
+                };
+
+                // Items are held in reverse, so each iteration pops the next
+                items.reverse();
+
+                if let Some(first_item) = items.pop() {
+                    self.variables[*item] = DialogueValue::Text(first_item);
+
+                    self.enter_block(
+                        step_index,
+                        Branch::Body,
+                        BlockRepeat::Each { item: *item, items },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Queues a log event.
+    fn log(&mut self, level: LogLevel, message: impl Into<String>) {
+        self.events.push_back(DialogueEvent::Log {
+            level,
+            message: message.into(),
+        });
+    }
+
+    /// Checks the result returned by a function's handler against the function's definition,
+    /// falling back to the definition's default result if the handler returned none or one of
+    /// the wrong type.
+    fn check_function_result(
+        &mut self,
+        function: usize,
+        value: Option<DialogueValue>,
+    ) -> Option<DialogueValue> {
+        let function = &self.program.functions[function];
+        let name = function.name.clone();
+        let default_result = function.result.clone();
+
+        match (value, default_result) {
+            (Some(value), Some(default_result)) if !value.is_same_type(&default_result) => {
+                self.log(
+                    LogLevel::Error,
+                    format!(
+                        "Function result type does not match definition ({name}: expected {}, got {})",
+                        default_result.type_name(),
+                        value.type_name()
+                    ),
+                );
+                Some(default_result)
+            }
+            (Some(_), None) => {
+                self.log(
+                    LogLevel::Warning,
+                    format!("Function has no result to return ({name})"),
+                );
+                None
+            }
+            (value, default_result) => value.or(default_result),
+        }
+    }
+
+    /// Evaluates a condition against the current variables, treating errors as false.
+    fn evaluate_condition(&mut self, condition: &CompiledExpression) -> bool {
+        match evaluate_expression(condition, &self.variables) {
+            Ok(value) => value.is_truthy(),
+            Err(error) => {
+                self.log(LogLevel::Error, error);
+                false
+            }
+        }
+    }
+
+    /// Resolves text by substituting any variables with literals of their current values,
+    /// keeping their styles.
+    fn resolve_text(&self, text: &CompiledText) -> DialogueText {
+        let spans = text
+            .spans
+            .iter()
+            .map(|span| {
+                let content = match &span.content {
+                    CompiledSegment::Literal(literal) => literal.clone(),
+                    CompiledSegment::Variable(slot) => self.variables[*slot].to_string(),
+                };
+
+                TextSpan {
+                    content: TextSegment::Literal(content),
+                    style: span.style.clone(),
+                }
+            })
+            .collect();
+
+        DialogueText { spans }
+    }
+}
+
+/// Gets the steps of the block at the given path.
+fn get_block_steps<'a>(path: &BlockPath, sections: &'a [CompiledSection]) -> &'a [CompiledStep] {
+    let mut steps = match sections.get(path.section) {
+        Some(section) => &section.steps[..],
+        None => return &[],
+    };
+
+    for (step_index, branch) in &path.nesting {
+        let nested_steps = match (steps.get(*step_index), branch) {
+            (Some(CompiledStep::Conditional { then_steps, .. }), Branch::Then) => then_steps,
+            (Some(CompiledStep::Conditional { else_steps, .. }), Branch::Else) => else_steps,
+            (
+                Some(
+                    CompiledStep::Repeat { steps, .. }
+                    | CompiledStep::While { steps, .. }
+                    | CompiledStep::Each { steps, .. },
+                ),
+                Branch::Body,
+            ) => steps,
+            (Some(CompiledStep::Page { responses, .. }), Branch::Response(response_index)) => {
+                match responses.get(*response_index) {
+                    Some(response) => &response.steps,
+                    None => return &[],
+                }
+            }
+            _ => return &[],
+        };
+
+        steps = nested_steps;
+    }
+
+    steps
+}
+
+/// Evaluates an expression against the current value of each variable.
+fn evaluate_expression(
+    expression: &CompiledExpression,
+    variables: &[DialogueValue],
+) -> Result<DialogueValue, String> {
+    match expression {
+        CompiledExpression::Value(value) => Ok(value.clone()),
+
+        CompiledExpression::Variable(slot) => variables
+            .get(*slot)
+            .cloned()
+            .ok_or_else(|| format!("Variable slot not found: {slot}")),
+
+        CompiledExpression::Unary { operator, operand } => {
+            let value = evaluate_expression(operand, variables)?;
+
+            match (operator, &value) {
+                (UnaryOperator::Not, _) => Ok(DialogueValue::Boolean(!value.is_truthy())),
+                (UnaryOperator::Negate, DialogueValue::Number(number)) => {
+                    Ok(DialogueValue::Number(-number))
+                }
+                (UnaryOperator::Negate, _) => Err(format!("Value cannot be negated: {value:?}")),
+            }
+        }
+
+        CompiledExpression::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let left = evaluate_expression(left, variables)?;
+
+            // Logical operators short-circuit, leaving the right side unevaluated
+            match operator {
+                BinaryOperator::And if !left.is_truthy() => {
+                    return Ok(DialogueValue::Boolean(false));
+                }
+                BinaryOperator::Or if left.is_truthy() => return Ok(DialogueValue::Boolean(true)),
+                _ => {}
+            }
+
+            let right = evaluate_expression(right, variables)?;
+
+            apply_binary_operator(&left, *operator, &right).ok_or_else(|| {
+                format!("Values cannot be operated on: {left:?} {operator:?} {right:?}")
+            })
+        }
+    }
+}
+
+/// Applies a binary operator to two values, returning `None` if the values don't support the
+/// operator.
+///
+/// Equality applies to all values, whereas ordering only applies to numbers and text. Adding text
+/// to any other value concatenates them.
+fn apply_binary_operator(
+    left: &DialogueValue,
+    operator: BinaryOperator,
+    right: &DialogueValue,
+) -> Option<DialogueValue> {
+    use DialogueValue::{Boolean, Number, Text};
+
+    let ordering = match (left, right) {
+        (Number(left), Number(right)) => left.partial_cmp(right),
+        (Text(left), Text(right)) => Some(left.cmp(right)),
+        _ => None,
+    };
+
+    let value = match (operator, left, right) {
+        (BinaryOperator::Add, Number(left), Number(right)) => Number(left + right),
+        (BinaryOperator::Add, Text(_), _) | (BinaryOperator::Add, _, Text(_)) => {
+            Text(format!("{left}{right}"))
+        }
+        (BinaryOperator::Subtract, Number(left), Number(right)) => Number(left - right),
+        (BinaryOperator::Multiply, Number(left), Number(right)) => Number(left * right),
+        (BinaryOperator::Divide, Number(left), Number(right)) => Number(left / right),
+        (BinaryOperator::Remainder, Number(left), Number(right)) => Number(left % right),
+        (BinaryOperator::Equal, _, _) => Boolean(left == right),
+        (BinaryOperator::NotEqual, _, _) => Boolean(left != right),
+        (BinaryOperator::Less, _, _) => Boolean(ordering?.is_lt()),
+        (BinaryOperator::LessOrEqual, _, _) => Boolean(ordering?.is_le()),
+        (BinaryOperator::Greater, _, _) => Boolean(ordering?.is_gt()),
+        (BinaryOperator::GreaterOrEqual, _, _) => Boolean(ordering?.is_ge()),
+        (BinaryOperator::And, _, _) => Boolean(left.is_truthy() && right.is_truthy()),
+        (BinaryOperator::Or, _, _) => Boolean(left.is_truthy() || right.is_truthy()),
+        _ => return None,
+    };
+
+    Some(value)
+}
@@ -0,0 +1,407 @@
+#[cfg(test)]
+use crate::*;
+
+#[cfg(test)]
+use std::collections::HashMap;
+
+/// Helper function for tests - compiles the given dialogue, panicking on diagnostics or errors
+fn compile_test_helper(input: &str) -> CompiledDialogue {
+    let result = parse(input.to_string());
+    if !result.diagnostics.is_empty() {
+        panic!("Unexpected diagnostics: {:?}", result.diagnostics);
+    }
+    compile(&result.dialogue).unwrap()
+}
+
+/// Helper function for tests - creates a runner for the given dialogue, panicking on diagnostics
+fn runner_test_helper(input: &str) -> DialogueRunner {
+    DialogueRunner::new(compile_test_helper(input))
+}
+
+/// Helper function for tests - gets the plain text of a line event, panicking on any other event
+fn line_text(event: DialogueEvent) -> String {
+    match event {
+        DialogueEvent::Line { text, .. } => text.plain_text(),
+        event => panic!("Expected a line, got {event:?}"),
+    }
+}
+
+/// Helper function for tests - runs dialogue to the end, making the given choices in order and
+/// collecting the text of each line
+fn run_lines(runner: &mut DialogueRunner, choices: &[usize]) -> Vec<String> {
+    let mut choices = choices.iter();
+    let mut lines = Vec::new();
+
+    loop {
+        match runner.next() {
+            DialogueEvent::Line { text, .. } => lines.push(text.plain_text()),
+            DialogueEvent::Choices { .. } => {
+                let choice = choices.next().expect("Ran out of choices");
+                runner.choose(*choice).unwrap();
+            }
+            DialogueEvent::End => return lines,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_runner_events() {
+    let input = r"
+@oscar
+name: Oscar Robin
+
+$gold: 5
+!save(slot=1)
+
+# intro
+oscar: Hello
+| You have {$gold} gold
+/// Entering the shop
+=><= !save(slot=2)
+Anything else?
+- Buy
+  $gold = $gold - 1
+  => shop
+- Leave
+  => END
+
+# shop
+You have {$gold} gold left";
+
+    let mut runner = runner_test_helper(input);
+
+    assert_eq!(
+        runner.next(),
+        DialogueEvent::Line {
+            speaker: Some("oscar".to_string()),
+            speaker_name: Some("Oscar Robin".to_string()),
+            text: DialogueText::literal("Hello"),
+            annotations: Default::default(),
+            starts_page: true,
+        }
+    );
+
+    let DialogueEvent::Line { starts_page, .. } = runner.next() else {
+        panic!("Expected a line");
+    };
+    assert!(!starts_page);
+
+    assert_eq!(
+        runner.next(),
+        DialogueEvent::Log {
+            level: LogLevel::Info,
+            message: "Entering the shop".to_string(),
+        }
+    );
+    assert_eq!(
+        runner.next(),
+        DialogueEvent::FunctionCall {
+            name: "save".to_string(),
+            args: HashMap::from([("slot".to_string(), DialogueValue::Number(2.0))]),
+        }
+    );
+    assert_eq!(line_text(runner.next()), "Anything else?");
+
+    // Choices are offered until one is made
+    let choices = DialogueEvent::Choices {
+        choices: vec![DialogueText::literal("Buy"), DialogueText::literal("Leave")],
+    };
+    assert_eq!(runner.next(), choices);
+    assert_eq!(runner.next(), choices);
+
+    assert_eq!(
+        runner.choose(2),
+        Err(ChoiceError::OutOfRange { index: 2, count: 2 })
+    );
+    assert_eq!(runner.choose(0), Ok(()));
+    assert_eq!(runner.choose(0), Err(ChoiceError::NoChoices));
+
+    assert_eq!(line_text(runner.next()), "You have 4 gold left");
+    assert_eq!(runner.variable("gold"), Some(&DialogueValue::Number(4.0)));
+
+    assert_eq!(runner.next(), DialogueEvent::End);
+    assert_eq!(runner.next(), DialogueEvent::End);
+    assert!(runner.is_finished());
+}
+
+#[test]
+fn test_runner_errors_are_logged() {
+    let input = r"
+$names: [Ann, Bob]
+
+# intro
+$names = 1
+~ EACH $names as $name
+  Hi {$name}
+~";
+
+    let mut runner = runner_test_helper(input);
+
+    assert_eq!(
+        runner.next(),
+        DialogueEvent::Log {
+            level: LogLevel::Error,
+            message: "Variable is not an array: names".to_string(),
+        }
+    );
+    assert_eq!(runner.next(), DialogueEvent::End);
+
+    // Entry sections are only known at runtime, so are checked as the runner starts
+    let mut runner = DialogueRunner::with_entry(compile_test_helper(input), "outro");
+    assert_eq!(
+        runner.next(),
+        DialogueEvent::Log {
+            level: LogLevel::Error,
+            message: "Section not found: outro".to_string(),
+        }
+    );
+    assert_eq!(runner.next(), DialogueEvent::End);
+}
+
+#[test]
+fn test_runner_endless_loops() {
+    let input = r"
+$count: 0
+
+# intro
+Hello
+~ WHILE true
+  $count = $count + 1
+~";
+
+    let mut runner = runner_test_helper(input);
+
+    assert_eq!(line_text(runner.next()), "Hello");
+
+    // Loops which never produce an event end the dialogue rather than never returning
+    assert!(matches!(
+        runner.next(),
+        DialogueEvent::Log {
+            level: LogLevel::Error,
+            ..
+        }
+    ));
+    assert_eq!(runner.next(), DialogueEvent::End);
+    assert!(runner.is_finished());
+}
+
+#[test]
+fn test_runner_repeated_steps() {
+    // Identical steps are told apart by their position rather than their content
+    let input = r"
+# intro
+Hello
+Hello
+=><= aside
+=><= aside
+Bye
+=> END
+=> END
+
+# aside
+Psst
+=> END";
+
+    let mut runner = runner_test_helper(input);
+
+    assert_eq!(
+        run_lines(&mut runner, &[]),
+        vec!["Hello", "Hello", "Psst", "Psst", "Bye"]
+    );
+}
+
+#[test]
+fn test_runner_blocks() {
+    let input = r"
+$count: 0
+$names: [Ann, Bob]
+
+# intro
+~ REPEAT 2
+  Repeat {$count}
+  $count = $count + 1
+~
+~ WHILE $count < 4
+  While {$count}
+  $count = $count + 1
+~
+~ EACH $names as $name
+  Hi {$name}
+~
+[if=$count == 4]
+  Pick one
+  - First
+    First chosen
+    => outro
+  - Second
+    Second chosen
+~
+After the block
+
+# outro
+Done";
+
+    let mut runner = runner_test_helper(input);
+
+    assert_eq!(
+        run_lines(&mut runner, &[0]),
+        vec![
+            "Repeat 0",
+            "Repeat 1",
+            "While 2",
+            "While 3",
+            "Hi Ann",
+            "Hi Bob",
+            "Pick one",
+            "First chosen",
+            "Done",
+        ]
+    );
+
+    // Sections run on into the next, as do blocks once a response's steps are done
+    let mut runner = runner_test_helper(input);
+
+    assert_eq!(
+        run_lines(&mut runner, &[1])[7..],
+        ["Second chosen", "After the block", "Done"]
+    );
+}
+
+#[test]
+fn test_runner_loop_items_are_scoped() {
+    let input = r"
+$name: Zed
+$names: [Ann, Bob]
+
+# intro
+~ EACH $names as $name
+  Hi {$name}
+~
+Bye {$name}";
+
+    let mut runner = runner_test_helper(input);
+
+    // Loop items shadow variables of the same name, rather than assigning to them
+    assert_eq!(
+        run_lines(&mut runner, &[]),
+        vec!["Hi Ann", "Hi Bob", "Bye Zed"]
+    );
+    assert_eq!(
+        runner.variable("name"),
+        Some(&DialogueValue::Text("Zed".to_string()))
+    );
+}
+
+#[test]
+fn test_runner_save_and_restore() {
+    let input = r"
+$gold: 5
+
+# intro
+Hello
+- Buy
+  $gold = $gold - 1
+  => shop
+- Leave
+  => END
+
+# shop
+=><= aside
+You have {$gold} gold left
+=> intro
+
+# aside
+Psst";
+
+    let program = compile_test_helper(input);
+    let mut runner = DialogueRunner::new(program.clone());
+
+    assert_eq!(line_text(runner.next()), "Hello");
+    runner.choose(0).unwrap();
+
+    // Save partway through the shop's bounce, with a line waiting to be returned
+    assert_eq!(line_text(runner.next()), "Psst");
+
+    let saved = serde_json::to_string(&runner.save_state()).unwrap();
+    let state: RunnerState = serde_json::from_str(&saved).unwrap();
+    let mut restored = DialogueRunner::restore(program.clone(), state.clone()).unwrap();
+
+    assert_eq!(restored.variable("gold"), Some(&DialogueValue::Number(4.0)));
+    assert_eq!(restored.visit_count("intro"), 1);
+    assert_eq!(restored.visit_count("shop"), 1);
+    assert_eq!(restored.visit_count("aside"), 1);
+
+    assert_eq!(line_text(restored.next()), "You have 4 gold left");
+    assert_eq!(line_text(restored.next()), "Hello");
+    assert_eq!(restored.visit_count("intro"), 2);
+
+    // States can't be restored into a dialogue which has since changed
+    let changed_program = compile_test_helper(&input.replace("Psst", "Pssst"));
+
+    assert_eq!(
+        DialogueRunner::restore(changed_program.clone(), state).unwrap_err(),
+        StateError::DialogueChanged {
+            saved_hash: program.hash,
+            current_hash: changed_program.hash,
+        }
+    );
+}
+
+#[test]
+fn test_runner_function_handlers() {
+    let input = r"
+$low: 0
+$high: 0
+$name: Nobody
+!roll(sides=6): 1
+!get_name: Unknown
+!save(slot=1)
+
+# intro
+$low = !roll()
+$high = !roll(sides=20)
+$name = !get_name()
+=><= !save(slot=2)
+=><= !save()";
+
+    let mut runner = runner_test_helper(input);
+
+    // Handlers are given every argument, including defaults
+    assert!(runner.register_function("Roll", |args: &HashMap<_, _>| {
+        args.get("sides").cloned()
+    }));
+    assert!(runner.register_function("get_name", |_: &HashMap<_, _>| {
+        Some(DialogueValue::Boolean(true))
+    }));
+    assert!(runner.register_function("save", |args: &HashMap<_, _>| {
+        (args.get("slot") == Some(&DialogueValue::Number(1.0)))
+            .then_some(DialogueValue::Number(1.0))
+    }));
+    assert!(!runner.register_function("missing", |_: &HashMap<_, _>| None));
+
+    // Results of the wrong type fall back to the default, as do results of functions without one
+    assert_eq!(
+        runner.next(),
+        DialogueEvent::Log {
+            level: LogLevel::Error,
+            message: "Function result type does not match definition (get_name: expected text, got boolean)"
+                .to_string(),
+        }
+    );
+    assert_eq!(
+        runner.next(),
+        DialogueEvent::Log {
+            level: LogLevel::Warning,
+            message: "Function has no result to return (save)".to_string(),
+        }
+    );
+    assert_eq!(runner.next(), DialogueEvent::End);
+
+    assert_eq!(runner.variable("low"), Some(&DialogueValue::Number(6.0)));
+    assert_eq!(runner.variable("high"), Some(&DialogueValue::Number(20.0)));
+    assert_eq!(
+        runner.variable("name"),
+        Some(&DialogueValue::Text("Unknown".to_string()))
+    );
+}